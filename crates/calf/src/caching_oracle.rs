@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::oracle_trait::{AutomatonTrait, OracleFailure, OracleTrait};
+
+/// Oracle wrapper memoizing membership queries, equivalence queries are forwarded as is.
+///
+/// Membership is a pure function of the word, so cached answers are never invalidated.
+/// Words are cached under their key, the word itself unless `with_key` sets another.
+pub struct CachingOracle<O: OracleTrait<String>> {
+    inner: O,
+    key: Box<dyn Fn(&str) -> String + Send + Sync>,
+    cache: Mutex<HashMap<String, bool>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+
+impl<O: OracleTrait<String>> CachingOracle<O> {
+    pub fn new(inner: O) -> Self {
+        CachingOracle {
+            inner,
+            key: Box::new(|word| word.to_string()),
+            cache: Mutex::new(HashMap::new()),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Caches words under `key(word)`, so words with the same key share one entry, e.g.
+    /// `str::to_lowercase` for an inner oracle ignoring case. The key must only merge words the
+    /// inner oracle answers the same, the first of them asked is the one forwarded.
+    pub fn with_key(mut self, key: impl Fn(&str) -> String + Send + Sync + 'static) -> Self {
+        self.key = Box::new(key);
+        self
+    }
+
+    /// Number of membership queries answered from the cache.
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of membership queries forwarded to the inner oracle.
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    pub fn into_inner(self) -> O {
+        self.inner
+    }
+}
+
+
+impl<O: OracleTrait<String>> OracleTrait<String> for CachingOracle<O> {
+    fn membership_query(&self, input: &String) -> bool {
+        let key = (self.key)(input);
+        if let Some(result) = self.cache.lock().unwrap().get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return *result;
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let result = self.inner.membership_query(input);
+        self.cache.lock().unwrap().insert(key, result);
+        result
+    }
+
+    fn equivalence_query<H: AutomatonTrait<String>>(&self, hypothesis: &H) -> Option<String> {
+        self.inner.equivalence_query(hypothesis)
+    }
+
+    fn try_equivalence_query<H: AutomatonTrait<String>>(&self, hypothesis: &H) -> Result<Option<String>, OracleFailure> {
+        self.inner.try_equivalence_query(hypothesis)
+    }
+
+    fn search_bound(&self) -> Option<usize> {
+        self.inner.search_bound()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // words of even length, counting the queries it answers per word
+    struct EvenLength {
+        asked: Mutex<HashMap<String, usize>>,
+    }
+
+    impl OracleTrait<String> for EvenLength {
+        fn membership_query(&self, input: &String) -> bool {
+            *self.asked.lock().unwrap().entry(input.clone()).or_default() += 1;
+            input.len().is_multiple_of(2)
+        }
+
+        fn equivalence_query<H: AutomatonTrait<String>>(&self, _hypothesis: &H) -> Option<String> {
+            None
+        }
+
+        fn search_bound(&self) -> Option<usize> {
+            Some(3)
+        }
+    }
+
+    #[test]
+    fn repeated_queries_are_answered_from_the_cache() {
+        let oracle = CachingOracle::new(EvenLength { asked: Mutex::new(HashMap::new()) });
+        let words = ["ab", "a", "ab", "", "a", "ab"];
+        let answers: Vec<bool> = words.iter().map(|word| oracle.membership_query(&word.to_string())).collect();
+
+        assert_eq!(answers, vec![true, false, true, true, false, true]);
+        assert_eq!(oracle.misses(), 3);
+        assert_eq!(oracle.hits(), 3);
+        let asked = oracle.into_inner().asked.into_inner().unwrap();
+        assert_eq!(asked.len(), 3);
+        assert!(asked.values().all(|count| *count == 1));
+    }
+
+    #[test]
+    fn words_with_the_same_key_share_an_entry() {
+        let oracle = CachingOracle::new(EvenLength { asked: Mutex::new(HashMap::new()) }).with_key(str::to_lowercase);
+        for word in ["Ab", "aB", "AB", "ab", "abc"] {
+            oracle.membership_query(&word.to_string());
+        }
+
+        assert_eq!(oracle.misses(), 2);
+        assert_eq!(oracle.hits(), 3);
+        let asked = oracle.into_inner().asked.into_inner().unwrap();
+        assert_eq!(asked, HashMap::from([("Ab".to_string(), 1), ("abc".to_string(), 1)]));
+    }
+
+    #[test]
+    fn search_bound_is_the_one_of_the_inner_oracle() {
+        let oracle = CachingOracle::new(EvenLength { asked: Mutex::new(HashMap::new()) });
+        assert_eq!(oracle.search_bound(), Some(3));
+    }
+}
@@ -1,6 +1,9 @@
 use std::hash::Hash;
+use std::marker::PhantomData;
 use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 use category_theory::core::traits::category_trait::{CategorySubObjectAlias, CategoryTrait, MorphismCommutationResult, CategoryFromObjects, CategoryCloneWithNewId};
 use category_theory::core::arrow::{Morphism, Arrow};
@@ -12,26 +15,107 @@ use category_theory::core::epic_monic_category::EpicMonicCategory;
 use category_theory::core::object_id::ObjectId;
 use category_theory::core::traits::factorization_system_trait::FactorizationSystemTrait;
 use crate::calf_errors::CalfErrors;
+use category_theory::core::errors::Errors;
 use category_theory::core::functors::inclusion_functor::inclusion_functor;
-use crate::oracle_trait::{OracleTrait, QueryInputTrait};
+use crate::oracle_trait::{AsyncOracleTrait, QueryInputTrait};
+use crate::learned_dfa::{LearnedDfa, TrivialLanguage};
 use category_theory::core::identifier::Identifier;
 use category_theory::core::persistable_category::PersistableCategory;
 use category_theory::core::persistable_factorization_category::PersistableFactorizationCategory;
 
+const OBSERVATION_TABLE_SCHEMA_VERSION: u32 = 1;
+
+const SESSION_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CounterexampleStrategy {
+    // adds every prefix of the counterexample to S, as in the original L*
+    AllPrefixes,
+    // adds the single distinguishing suffix found by Rivest-Schapire's binary search to E
+    RivestSchapire,
+}
+
+// query cost of a run, for comparing oracles and counterexample strategies
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LearningStats {
+    // queries actually sent to the oracle, cache hits are not counted
+    pub membership_queries: usize,
+    pub equivalence_queries: usize,
+    // times the table was found not closed resp. not consistent and had to be extended
+    pub closedness_rounds: usize,
+    pub consistency_rounds: usize,
+    // set when the accepted hypothesis accepts no word or every word, which is more often a
+    // broken oracle than the target language. a warning only, the run still converges.
+    pub trivial_language: Option<TrivialLanguage>,
+}
+
+// how a budgeted run ended. when the budget runs out the current hypothesis is returned, the one
+// the next equivalence query would have checked. the query budget can run out while the table is
+// not closed, then there is no hypothesis.
+#[derive(Clone, Debug)]
+pub enum RunOutcome {
+    Converged,
+    RoundsExhausted(LearnedDfa),
+    QueryBudgetExhausted(Option<LearnedDfa>),
+}
+
+// what the table gained from the last counterexample processed. states are the distinct rows of
+// S and S.A, each named by its shortest word, so a new state of S.A is one the next closedness
+// round moves to S.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CounterexampleEffect {
+    pub counterexample: String,
+    // words added to S resp. E, sorted
+    pub added_prefixes: Vec<String>,
+    pub added_suffixes: Vec<String>,
+    // states not told apart before the counterexample, sorted
+    pub new_states: Vec<String>,
+}
+
+// what ends a run other than the oracle accepting a hypothesis
+struct RunLimits {
+    deadline: Option<Instant>,
+    max_rounds: Option<usize>,
+    max_queries: Option<usize>,
+    // the stats when the run started, queries are counted from there
+    start: LearningStats,
+}
+
+
+impl RunLimits {
+    fn new(start: LearningStats) -> Self {
+        RunLimits { deadline: None, max_rounds: None, max_queries: None, start }
+    }
+
+    fn check_deadline(&self) -> Result<(), CalfErrors> {
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Err(CalfErrors::Timeout),
+            _ => Ok(()),
+        }
+    }
+
+    fn queries_exhausted(&self, stats: &LearningStats) -> bool {
+        let queries = (stats.membership_queries - self.start.membership_queries)
+            + (stats.equivalence_queries - self.start.equivalence_queries);
+        self.max_queries.is_some_and(|max_queries| queries >= max_queries)
+    }
+}
+
 enum Closed<Category: CategoryTrait> {
     Closed,
     NotClosed(HashSet<Arc<Category::Morphism>>),
 }
 
 
-enum Consistent<Category: CategoryTrait> {
+enum Consistent {
     Consistent,
-    NotConsistent(HashSet<Arc<Category::Morphism>>),
+    // suffixes telling apart two prefixes with equal rows
+    NotConsistent(Vec<String>),
 }
 
 
 pub struct CALF<
-    Oracle: OracleTrait<String>,
+    Oracle: AsyncOracleTrait<String>,
     BaseCategory: CategoryTrait<
         Morphism = Arrow<<BaseCategory as CategoryTrait>::Object,
             <BaseCategory as CategoryTrait>::Object>> + Hash + Eq + Clone
@@ -65,12 +149,61 @@ where
     suffix_power_set: Arc<BaseCategory::Object>,
 
     oracle: Oracle,
+
+    // writes progress to log when set, off by default
+    verbose: bool,
+    // stdout unless set_log_writer is called
+    log: Arc<Mutex<dyn Write + Send>>,
+
+    // answers of the oracle keyed by the queried word.
+    // membership is a pure function of the word so entries never need invalidating.
+    membership_cache: HashMap<String, bool>,
+    cache_membership: bool,
+    cache_hits: usize,
+    cache_misses: usize,
+
+    stats: LearningStats,
+
+    // the oracle accepted the hypothesis of the current table, reset whenever the table changes
+    hypothesis_accepted: bool,
+
+    last_counterexample_effect: Option<CounterexampleEffect>,
+
+    counterexample_strategy: CounterexampleStrategy,
+
+    // placed between symbols in the words sent to the oracle, empty by default
+    symbol_separator: String,
+
+    // longer counterexamples are rejected rather than added to the table, unbounded by default
+    max_counterexample_length: Option<usize>,
+
+    // the category is exported here as cytoscape json once a run converges
+    export_path: Option<String>,
+}
+
+
+// the objects making up the observation table at some point of a run, with the membership
+// cache and the counters, so a restored run asks and counts exactly what it did the first time.
+// the category only grows, so the morphisms between these objects are still there on restore.
+#[derive(Clone)]
+pub struct CalfSnapshot<BaseCategory: CategoryTrait> {
+    prefix: Arc<BaseCategory::Object>,
+    prefix_alphabet: Arc<BaseCategory::Object>,
+    hypothesis_prefix_alphabet: Arc<BaseCategory::Object>,
+    suffix: Arc<BaseCategory::Object>,
+    suffix_power_set: Arc<BaseCategory::Object>,
+    membership_cache: HashMap<String, bool>,
+    cache_hits: usize,
+    cache_misses: usize,
+    stats: LearningStats,
+    hypothesis_accepted: bool,
+    last_counterexample_effect: Option<CounterexampleEffect>,
 }
 
 
 impl <Oracle, BaseCategory> CALF<Oracle, BaseCategory>
 where
-    Oracle: OracleTrait<String>,
+    Oracle: AsyncOracleTrait<String>,
     BaseCategory: CategoryTrait<Morphism = Arrow<<BaseCategory as CategoryTrait>::Object, <BaseCategory as CategoryTrait>::Object>> + Hash + Eq + Clone ,
     BaseCategory::Object: Clone + From<String> + CategoryCloneWithNewId,
     <BaseCategory::Object as CategoryTrait>::Object : Clone + From<String> + for<'a> From<&'a str>,
@@ -78,25 +211,49 @@ where
 {
     pub async fn new(alphabets: Arc<BaseCategory::Object>, oracle: Oracle) -> Self
     {
+        Self::try_new(alphabets, oracle).await.expect("Failed to create CALF")
+    }
+
+    pub async fn try_new(alphabets: Arc<BaseCategory::Object>, oracle: Oracle) -> Result<Self, CalfErrors>
+    {
+        let mut calf = Self::with_empty_table(alphabets, oracle).await?;
+        calf.fill_table().await?;
+        Ok(calf)
+    }
+
+    async fn with_empty_table(alphabets: Arc<BaseCategory::Object>, oracle: Oracle) -> Result<Self, CalfErrors>
+    {
+        // S and E only hold the empty word and nothing is asked to the oracle yet, so the
+        // settings shaping the queries can still be changed before fill_table
+        Self::validate_alphabet(&alphabets).await?;
+
         let mut category =
-            PersistableFactorizationCategory::new().await.unwrap();
+            PersistableFactorizationCategory::new().await?;
         // add alphabet object to the category
-        category.add_object(alphabets.clone()).await.expect("Failed to add alphabet object");
+        category.add_object(alphabets.clone()).await?;
 
         // add prefix and suffix with empty.
-        let prefix: Arc<BaseCategory::Object> = Arc::new(BaseCategory::Object::from_objects(vec![""]).await.unwrap());
-        category.add_object(prefix.clone()).await.expect("Failed to add prefix");
+        let prefix: Arc<BaseCategory::Object> = Arc::new(BaseCategory::Object::from_objects(vec![""]).await?);
+        category.add_object(prefix.clone()).await?;
+
+        let suffix: Arc<BaseCategory::Object> = Arc::new(BaseCategory::Object::from_objects(vec![""]).await?);
+        category.add_object(suffix.clone()).await?;
 
-        let suffix: Arc<BaseCategory::Object> = Arc::new(BaseCategory::Object::from_objects(vec![""]).await.unwrap());
-        category.add_object(suffix.clone()).await.expect("Failed to add suffix");
+        // the rest of the algorithm assumes the empty word in both S and E
+        if !Self::contains_empty_word(&prefix).await {
+            return Err(CalfErrors::MissingEmptyWord("prefix".to_string()));
+        }
+        if !Self::contains_empty_word(&suffix).await {
+            return Err(CalfErrors::MissingEmptyWord("suffix".to_string()));
+        }
 
-        let suffix_power_set: Arc<BaseCategory::Object> =Arc::new(BaseCategory::Object::from_objects(vec![""]).await.unwrap());
-        category.add_object(suffix_power_set.clone()).await.expect("Failed to add observations");
+        let suffix_power_set: Arc<BaseCategory::Object> =Arc::new(BaseCategory::Object::from_objects(vec![""]).await?);
+        category.add_object(suffix_power_set.clone()).await?;
 
 
-        let prefix_alphabet = Arc::new(BaseCategory::Object::new().await.unwrap());
+        let prefix_alphabet = Arc::new(BaseCategory::Object::new().await?);
 
-        let mut result = CALF {
+        Ok(CALF {
             category,
             prefix,
             suffix,
@@ -104,17 +261,239 @@ where
             oracle,
             suffix_power_set,
             prefix_alphabet,
-            hypothesis_prefix_alphabet: Arc::new(BaseCategory::Object::new().await.unwrap()),
-        };
-        result.create_suffix_power_set().await.unwrap();
+            hypothesis_prefix_alphabet: Arc::new(BaseCategory::Object::new().await?),
+            verbose: false,
+            log: Arc::new(Mutex::new(io::stdout())),
+            membership_cache: HashMap::new(),
+            cache_membership: true,
+            cache_hits: 0,
+            cache_misses: 0,
+            stats: LearningStats::default(),
+            hypothesis_accepted: false,
+            last_counterexample_effect: None,
+            counterexample_strategy: CounterexampleStrategy::RivestSchapire,
+            symbol_separator: String::new(),
+            max_counterexample_length: None,
+            export_path: None,
+        })
+    }
+
+    async fn fill_table(&mut self) -> Result<(), CalfErrors>
+    {
+        // 2^E holds the rows of S and S.A, so both are rebuilt whenever S or E changes
+        self.hypothesis_accepted = false;
+        self.create_suffix_power_set().await?;
         // order matters here since in prefix alphabet we need suffix power set to be initialized first
-        result.create_prefix_alphabet().await.unwrap();
-        result
+        self.create_prefix_alphabet().await
+    }
+
+    async fn validate_alphabet(alphabets: &Arc<BaseCategory::Object>) -> Result<(), CalfErrors>
+    {
+        // the alphabet has to be a non empty set of non empty symbols. it is a set, so symbols
+        // given twice are caught on the list it is built from, see check_distinct_symbols
+        let symbols: Vec<String> = alphabets.get_all_objects().await?.into_iter()
+            .map(|symbol| symbol.category_id().to_string())
+            .collect();
+        if symbols.is_empty() {
+            return Err(CalfErrors::EmptyAlphabet);
+        }
+        if symbols.iter().any(|symbol| symbol.is_empty()) {
+            return Err(CalfErrors::InvalidAlphabet("alphabet contains the empty word".to_string()));
+        }
+        // words of the table are plain concatenations of symbols, so two sequences of symbols
+        // spelling the same word would share a row e.g. a.b and ab over {a, b, ab}
+        if !uniquely_decodable(&symbols) {
+            return Err(CalfErrors::InvalidAlphabet(format!("some word is spelled by two sequences of {:?}", symbols)));
+        }
+        Ok(())
+    }
+
+    pub fn set_verbose(&mut self, verbose: bool)
+    {
+        self.verbose = verbose;
+    }
+
+    /// Sets where the progress is written when verbose, stdout by default.
+    pub fn set_log_writer(&mut self, log: impl Write + Send + 'static)
+    {
+        self.log = Arc::new(Mutex::new(log));
+    }
+
+    fn log(&self, message: std::fmt::Arguments)
+    {
+        // progress is informative only, so failing to write it does not fail the run
+        if let Ok(mut log) = self.log.lock() {
+            let _ = writeln!(log, "{}", message);
+        }
+    }
+
+    pub fn set_counterexample_strategy(&mut self, counterexample_strategy: CounterexampleStrategy)
+    {
+        self.counterexample_strategy = counterexample_strategy;
+    }
+
+    /// Sets the separator put between symbols in the words sent to the oracle. The initial table
+    /// is filled on creation, so use `CalfBuilder::symbol_separator` to have it apply from the start.
+    pub fn set_symbol_separator(&mut self, symbol_separator: &str)
+    {
+        self.symbol_separator = symbol_separator.to_string();
+    }
+
+    pub fn set_membership_cache(&mut self, cache_membership: bool)
+    {
+        self.cache_membership = cache_membership;
+    }
+
+    pub fn set_max_counterexample_length(&mut self, max_counterexample_length: usize)
+    {
+        self.max_counterexample_length = Some(max_counterexample_length);
+    }
+
+    pub fn set_export_path(&mut self, export_path: &str)
+    {
+        self.export_path = Some(export_path.to_string());
+    }
+
+    pub fn snapshot(&self) -> CalfSnapshot<BaseCategory>
+    {
+        CalfSnapshot {
+            prefix: self.prefix.clone(),
+            prefix_alphabet: self.prefix_alphabet.clone(),
+            hypothesis_prefix_alphabet: self.hypothesis_prefix_alphabet.clone(),
+            suffix: self.suffix.clone(),
+            suffix_power_set: self.suffix_power_set.clone(),
+            membership_cache: self.membership_cache.clone(),
+            cache_hits: self.cache_hits,
+            cache_misses: self.cache_misses,
+            stats: self.stats,
+            hypothesis_accepted: self.hypothesis_accepted,
+            last_counterexample_effect: self.last_counterexample_effect.clone(),
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: CalfSnapshot<BaseCategory>)
+    {
+        self.prefix = snapshot.prefix;
+        self.prefix_alphabet = snapshot.prefix_alphabet;
+        self.hypothesis_prefix_alphabet = snapshot.hypothesis_prefix_alphabet;
+        self.suffix = snapshot.suffix;
+        self.suffix_power_set = snapshot.suffix_power_set;
+        self.membership_cache = snapshot.membership_cache;
+        self.cache_hits = snapshot.cache_hits;
+        self.cache_misses = snapshot.cache_misses;
+        self.stats = snapshot.stats;
+        self.hypothesis_accepted = snapshot.hypothesis_accepted;
+        self.last_counterexample_effect = snapshot.last_counterexample_effect;
     }
 
     pub async fn run(&mut self) -> Result<(), CalfErrors>
     {
+        // without limits the run only ends once the oracle accepts a hypothesis
+        self.run_until(RunLimits::new(self.stats)).await.map(|_| ())
+    }
+
+    // the deadline is only checked between table updates, so on a timeout the table is
+    // left as it was after the last complete update and can still be exported.
+    pub async fn run_with_timeout(&mut self, duration: Duration) -> Result<(), CalfErrors>
+    {
+        let limits = RunLimits { deadline: Some(Instant::now() + duration), ..RunLimits::new(self.stats) };
+        self.run_until(limits).await.map(|_| ())
+    }
+
+    // a round is one equivalence query together with the table updates before it.
+    // queries are counted from the start of this call, membership and equivalence alike, and
+    // checked between table updates, so a single update may take the count past the budget.
+    pub async fn run_with_budget(&mut self, max_rounds: usize, max_queries: usize) -> Result<RunOutcome, CalfErrors>
+    {
+        let limits = RunLimits {
+            max_rounds: Some(max_rounds),
+            max_queries: Some(max_queries),
+            ..RunLimits::new(self.stats)
+        };
+        self.run_until(limits).await
+    }
+
+    /// Whether learning is over: the table is closed and consistent, and the last equivalence
+    /// query accepted its hypothesis. Tells a caller driving the run in steps whether another one is needed.
+    pub async fn is_converged(&mut self) -> Result<bool, CalfErrors>
+    {
+        if !self.hypothesis_accepted {
+            return Ok(false);
+        }
+        Ok(matches!(self.is_closed().await?, Closed::Closed)
+            && matches!(self.is_consistent().await?, Consistent::Consistent))
+    }
+
+    async fn run_until(&mut self, limits: RunLimits) -> Result<RunOutcome, CalfErrors>
+    {
+        let mut rounds = 0;
+        loop {
+            if !self.close_and_make_consistent(&limits).await? {
+                // the table may have been left not closed, in which case there is no hypothesis
+                return Ok(RunOutcome::QueryBudgetExhausted(self.extract_automaton().await.ok()));
+            }
+
+            // the table is closed and consistent, check the hypothesis against the oracle
+            let hypothesis = self.build_hypothesis().await?;
+            limits.check_deadline()?;
+            if limits.max_rounds.is_some_and(|max_rounds| rounds >= max_rounds) {
+                return Ok(RunOutcome::RoundsExhausted(hypothesis));
+            }
+            if limits.queries_exhausted(&self.stats) {
+                return Ok(RunOutcome::QueryBudgetExhausted(Some(hypothesis)));
+            }
+            rounds += 1;
+            self.stats.equivalence_queries += 1;
+            let counterexample = AsyncOracleTrait::equivalence_query(&self.oracle, &hypothesis).await
+                .map_err(CalfErrors::EquivalenceQueryFailed)?;
+            match counterexample {
+                None => {
+                    self.hypothesis_accepted = true;
+                    self.stats.trivial_language = hypothesis.trivial_language();
+                    if let Some(language) = self.stats.trivial_language
+                        && self.verbose {
+                        self.log(format_args!("Warning: the learned language is {:?}, check the oracle", language));
+                    }
+                    break;
+                },
+                Some(counterexample) => self.process_counterexample(&counterexample).await?,
+            }
+        }
+
+        let final_hypothesis_transition = self.get_or_add_hypothesis_transition().await?;
+
+        if self.verbose {
+            // print states
+            let states = final_hypothesis_transition.target_object().get_all_objects().await?;
+            self.log(format_args!("States: {:?}", states));
+
+            // print transitions
+            let transitions = final_hypothesis_transition.arrow_mappings();
+            self.log(format_args!("Transitions: {:?}", transitions));
+        }
+        self.export_if_configured().await?;
+        Ok(RunOutcome::Converged)
+    }
+
+    async fn export_if_configured(&self) -> Result<(), CalfErrors>
+    {
+        match &self.export_path {
+            Some(export_path) => self.export_cytoscape(export_path).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn close_and_make_consistent(&mut self, limits: &RunLimits) -> Result<bool, CalfErrors>
+    {
+        // false if the query budget ran out before the table was closed and consistent
         loop {
+            limits.check_deadline()?;
+            if limits.queries_exhausted(&self.stats) {
+                return Ok(false);
+            }
+            debug_assert!(
+                Self::contains_empty_word(&self.prefix).await && Self::contains_empty_word(&self.suffix).await,
+                "empty word missing from prefix or suffix");
 
             match self.is_closed().await? {
                 Closed::Closed => {
@@ -126,21 +505,25 @@ where
                 },
                 Closed::NotClosed(non_closed_morphisms) => {
                     // if not closed, then we need to add a new prefix
+                    self.stats.closedness_rounds += 1;
                     let new_prefix = self.update_table(
                         &self.prefix.clone(), non_closed_morphisms).await?;
                     self.prefix = new_prefix;
-                    self.create_prefix_alphabet().await?;
+                    self.ensure_empty_word().await?;
+                    self.fill_table().await?;
                     continue;
                 },
             }
 
             match self.is_consistent().await? {
-                Consistent::NotConsistent(non_consistent_morphisms) => {
+                Consistent::NotConsistent(distinguishing_suffixes) => {
                     // if not consistent, then we need to add a new suffix
-                    let new_suffix = self.update_table(
-                        &self.suffix.clone(), non_consistent_morphisms).await?;
-                    self.suffix = new_suffix;
-                    self.create_suffix_power_set().await?;
+                    self.stats.consistency_rounds += 1;
+                    if let Some(new_suffix) = self.insert_words(&self.suffix.clone(), distinguishing_suffixes).await? {
+                        self.suffix = new_suffix;
+                    }
+                    self.ensure_empty_word().await?;
+                    self.fill_table().await?;
                 },
                 Consistent::Consistent => {
                     // if consistent and closed, then we can stop
@@ -151,18 +534,218 @@ where
 
             }
         }
-        let final_hypothesis_transition = self.get_or_add_hypothesis_transition().await?;
+        Ok(true)
+    }
+
+    pub async fn process_counterexample(&mut self, counterexample: &str) -> Result<(), CalfErrors>
+    {
+        if let Some(max_counterexample_length) = self.max_counterexample_length {
+            if self.split_query(counterexample).await?.len() > max_counterexample_length {
+                return Err(CalfErrors::InvalidCounterexample(counterexample.to_string()));
+            }
+        }
+        let prefixes = Self::object_words(&self.prefix).await?;
+        let suffixes = Self::object_words(&self.suffix).await?;
+        let states: HashSet<String> = self.table_states().await?.into_values().collect();
+        match self.counterexample_strategy {
+            CounterexampleStrategy::AllPrefixes => self.add_counterexample_prefixes(counterexample).await?,
+            CounterexampleStrategy::RivestSchapire => self.add_distinguishing_suffix(counterexample).await?,
+        }
+
+        let added = |before: &HashSet<String>, after: HashSet<String>| {
+            let mut added: Vec<String> = after.into_iter().filter(|word| !before.contains(word)).collect();
+            added.sort();
+            added
+        };
+        self.last_counterexample_effect = Some(CounterexampleEffect {
+            counterexample: counterexample.to_string(),
+            added_prefixes: added(&prefixes, Self::object_words(&self.prefix).await?),
+            added_suffixes: added(&suffixes, Self::object_words(&self.suffix).await?),
+            new_states: added(&states, self.table_states().await?.into_values().collect()),
+        });
+        Ok(())
+    }
+
+    /// What the table gained from the last counterexample processed, `None` before the first.
+    pub fn last_counterexample_effect(&self) -> Option<CounterexampleEffect>
+    {
+        self.last_counterexample_effect.clone()
+    }
+
+    async fn object_words(object: &Arc<BaseCategory::Object>) -> Result<HashSet<String>, CalfErrors>
+    {
+        Ok(object.get_all_objects().await?.into_iter()
+            .map(|word| word.category_id().to_string())
+            .collect())
+    }
+
+    async fn table_states(&mut self) -> Result<HashMap<String, String>, CalfErrors>
+    {
+        // every distinct row of S and S.A, keyed by the row and named by the shortest word with it
+        self.get_or_create_morphism_to_powerset().await?;
+        let mut words: Vec<(String, String)> = self.observation_rows(&self.prefix).await?.into_iter()
+            .chain(self.observation_rows(&self.prefix_alphabet).await?)
+            .collect();
+        words.sort_by(|(a, _), (b, _)| a.len().cmp(&b.len()).then(a.cmp(b)));
+        let mut states = HashMap::new();
+        for (word, row) in words {
+            states.entry(row).or_insert(word);
+        }
+        Ok(states)
+    }
+
+    async fn add_distinguishing_suffix(&mut self, counterexample: &str) -> Result<(), CalfErrors>
+    {
+        let suffix = self.analyze_counterexample(counterexample).await?;
+        let suffix_words = suffix.get_all_objects().await?.into_iter()
+            .map(|word| word.category_id().to_string())
+            .collect();
+        match self.insert_words(&self.suffix.clone(), suffix_words).await? {
+            Some(new_suffix) => {
+                self.suffix = new_suffix;
+                self.ensure_empty_word().await?;
+                self.fill_table().await?;
+                Ok(())
+            },
+            // the suffix is already in E so the counterexample does not refine the table
+            None => Err(CalfErrors::InvalidCounterexample(counterexample.to_string())),
+        }
+    }
+
+    pub async fn analyze_counterexample(&mut self, counterexample: &str) -> Result<Arc<BaseCategory::Object>, CalfErrors>
+    {
+        /*
+        Rivest-Schapire counterexample analysis.
+
+        for a counterexample u = a1...an let
+            alpha(i) = membership of access(a1...ai) . ai+1...an
+        where access(x) is the word in S reaching the same hypothesis state as x.
+        alpha(0) is the membership of the counterexample and alpha(n) is the hypothesis output on it,
+        so they differ and a binary search finds i with alpha(i) != alpha(i+1).
+        ai+2...an then tells apart two words the hypothesis merged and is the only suffix added to E.
+
+        only membership queries are asked, the table is left as is. the suffix is returned as an
+        object with the single word so it can be added to E with update_table.
+         */
+        let symbols = self.split_query(counterexample).await
+            .map_err(|_| CalfErrors::InvalidCounterexample(counterexample.to_string()))?;
+        let hypothesis = self.build_hypothesis().await?;
+
+        // the shortest word of S with each row is the access string of that state
+        let mut upper_words: Vec<(String, String)> = self.observation_rows(&self.prefix).await?.into_iter().collect();
+        upper_words.sort_by(|(a, _), (b, _)| a.len().cmp(&b.len()).then(a.cmp(b)));
+        let mut access_strings: HashMap<String, String> = HashMap::new();
+        for (word, row) in upper_words {
+            access_strings.entry(row).or_insert(word);
+        }
+
+        let mut low = 0;
+        let mut high = symbols.len();
+        let low_value = self.rivest_schapire_value(&hypothesis, &access_strings, &symbols, low).await?;
+        if low_value == self.rivest_schapire_value(&hypothesis, &access_strings, &symbols, high).await? {
+            return Err(CalfErrors::InvalidCounterexample(counterexample.to_string()));
+        }
+        while high - low > 1 {
+            let middle = (low + high) / 2;
+            if self.rivest_schapire_value(&hypothesis, &access_strings, &symbols, middle).await? == low_value {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+
+        let suffix = symbols[high..].concat();
+        Ok(Arc::new(BaseCategory::Object::from_objects(vec![suffix.as_str()]).await?))
+    }
 
-        // print states
-        let states = final_hypothesis_transition.target_object();
-        println!("States: {:?}", states.get_all_objects().await?);
+    async fn rivest_schapire_value(
+        &mut self,
+        hypothesis: &LearnedDfa,
+        access_strings: &HashMap<String, String>,
+        symbols: &[String],
+        index: usize,
+    ) -> Result<bool, CalfErrors>
+    {
+        let state = hypothesis.run(&symbols[..index])
+            .ok_or_else(|| CalfErrors::UnknownState(symbols[..index].concat()))?;
+        let access_string = access_strings.get(state)
+            .ok_or_else(|| CalfErrors::UnknownState(state.clone()))?;
+        let query = self.query_word(&[access_string, &symbols[index..].concat()]).await?;
+        Ok(self.membership_query(&query).await)
+    }
 
-        // print transitions
-        let transitions = final_hypothesis_transition.arrow_mappings();
-        println!("Transitions: {:?}", transitions);
+    async fn add_counterexample_prefixes(&mut self, counterexample: &str) -> Result<(), CalfErrors>
+    {
+        // classic L*: every prefix of the counterexample is added to S
+        let previous_prefix = self.prefix.clone();
+        self.add_prefix(counterexample).await.map_err(|error| match error {
+            CalfErrors::InvalidWord(_) => CalfErrors::InvalidCounterexample(counterexample.to_string()),
+            error => error,
+        })?;
+
+        // a real counterexample is never entirely in S, since the hypothesis agrees with the table on S
+        if Arc::ptr_eq(&previous_prefix, &self.prefix) {
+            return Err(CalfErrors::InvalidCounterexample(counterexample.to_string()));
+        }
         Ok(())
     }
 
+    async fn build_hypothesis(&mut self) -> Result<LearnedDfa, CalfErrors>
+    {
+        self.get_or_create_morphism_to_powerset().await?;
+        self.extract_automaton().await
+    }
+
+    pub async fn extract_automaton(&self) -> Result<LearnedDfa, CalfErrors>
+    {
+        /*
+        reads the hypothesis off the closed and consistent table:
+            states are the distinct rows of S
+            the initial state is the row of the empty word
+            a state is accepting if its entry for the empty suffix is true
+            the transition on a from the row of s goes to the row of s.a
+
+        the table has to be filled, which is the case after run.
+         */
+        let suffixes = self.suffix_columns().await?;
+        let empty_suffix = suffixes.iter().position(|suffix| suffix.is_empty())
+            .ok_or(CalfErrors::UnknownError)?;
+        let upper_rows = self.observation_rows(&self.prefix).await?;
+        let lower_rows = self.observation_rows(&self.prefix_alphabet).await?;
+
+        let mut alphabet: Vec<String> = self.alphabets.get_all_objects().await?.into_iter()
+            .map(|symbol| symbol.category_id().to_string())
+            .collect();
+        alphabet.sort();
+
+        let initial = upper_rows.get("").ok_or_else(|| CalfErrors::UnknownState("".to_string()))?.clone();
+        let states: HashSet<String> = upper_rows.values().cloned().collect();
+        let accepting = states.iter()
+            .filter(|row| parse_row(row).get(empty_suffix) == Some(&true))
+            .cloned()
+            .collect();
+
+        let mut transitions = HashMap::new();
+        for (word, row) in &upper_rows {
+            for symbol in &alphabet {
+                let extended_word = format!("{}{}", word, symbol);
+                let target = lower_rows.get(&extended_word).or_else(|| upper_rows.get(&extended_word))
+                    .ok_or_else(|| CalfErrors::UnknownState(extended_word.clone()))?;
+                if !states.contains(target) {
+                    return Err(CalfErrors::HypothesisNotClosed(extended_word));
+                }
+                if let Some(existing_target) = transitions.insert((row.clone(), symbol.clone()), target.clone()) {
+                    if &existing_target != target {
+                        return Err(CalfErrors::HypothesisNotConsistent(extended_word));
+                    }
+                }
+            }
+        }
+
+        Ok(LearnedDfa::new(states, alphabet, transitions, initial, accepting)
+            .with_symbol_separator(&self.symbol_separator))
+    }
+
     pub async fn is_closed(&mut self) -> Result<Closed<BaseCategory::Object>, CalfErrors>
     {
         /*
@@ -178,65 +761,30 @@ where
         H ---------------m-----------------> 2^E
 
          */
-        // start by checking if there is a morphism from FS to 2^e
-        // there should be only one morphism from FS to 2^E
+        // FS is closed iff every row of FS is a row of H, which is what building closeW checks
         let powerset_morphisms =
             self.get_or_create_morphism_to_powerset().await?;
         let prefix_to_powerset = powerset_morphisms.0.clone();
         let prefix_alphabet_to_power_set = powerset_morphisms.1.clone();
 
+        let prefix_alphabet_to_h = match self.build_or_get_prefix_alphabet_to_hypothesis().await {
+            Ok(prefix_alphabet_to_h) => prefix_alphabet_to_h,
+            Err(CalfErrors::HypothesisNotClosed(word)) => {
+                let not_closed = prefix_alphabet_to_power_set.arrow_mappings().into_iter().flatten()
+                    .map(|(source_morphism, _)| source_morphism.clone())
+                    .filter(|source_morphism| source_morphism.source_object().category_id().to_string() == word)
+                    .collect();
+                return Ok(Closed::NotClosed(not_closed));
+            },
+            Err(error) => return Err(error),
+        };
 
-        // Since this is a factorisation system there should be a morphism from S to H and H to 2^e
-        // where its epic and monic respectively.
-        // now we need to check if there exist a morphism from FS to H.
-
-        // get epic and monic morphisms from prefix to powerset
-        let morphism_factors = self.category.morphism_factors(&*prefix_to_powerset)?;
+        // the epic and monic factors of S -> 2^E, which closeW was built against
+        let morphism_factors = self.category.morphism_factors(&*prefix_to_powerset)
+            .map_err(Self::factorization_failed(&prefix_to_powerset))?;
         let epic_morphism = morphism_factors.0.clone();
         let monic_morphism = morphism_factors.1.clone();
 
-        // now we need to check if there is a morphism from FS to H
-        let prefix_alphabet_to_h_homset = self.category.get_hom_set(&*self.prefix_alphabet,
-                                                      &**epic_morphism.target_object()).await?;
-
-        // there should be not more than one morphism from FS to H
-        if prefix_alphabet_to_h_homset.len() > 1 {
-            return Err(CalfErrors::MultipleMorphismsFromFSToH);
-        }
-
-        let prefix_alphabet_to_h = if prefix_alphabet_to_h_homset.is_empty() {
-            // if there is no morphism, then we need to create one
-            // create a new morphism from FS to H
-
-            // since from H to powerset is monic
-            // our mapping will map each obect in FS to object in H such that H maps to powerset
-            let mut prefix_alphabet_to_h_mapping = HashMap::new();
-
-            let monic_powerset_reverse_mapping: HashMap<_,_> = monic_morphism.arrow_mappings().into_iter().flatten()
-                .map(|(source, target)| (target.clone(), source.clone())).collect();
-            for (source_morphism, target_morphism) in prefix_alphabet_to_power_set.arrow_mappings().into_iter().flatten() {
-                // map each source morphism to a morphism in H
-                // get morphism in monic morphism that maps to the target morphism
-                if let Some(h_source_morphism) = monic_powerset_reverse_mapping.get(target_morphism) {
-                    prefix_alphabet_to_h_mapping.insert(source_morphism.clone(), h_source_morphism.clone());
-                }
-                else{
-                    // if there is no matching morphism in H, then its not closed
-                    return Ok(Closed::NotClosed(HashSet::from_iter([source_morphism.clone()])));
-                }
-
-            }
-
-            let morphism = Arc::new(Morphism::new_with_mappings(
-                self.prefix_alphabet.clone(),
-                epic_morphism.target_object().clone(),
-                prefix_alphabet_to_h_mapping
-            ));
-            self.category.add_morphism(morphism.clone()).await?;
-            morphism.clone()
-        } else {
-            (*prefix_alphabet_to_h_homset.iter().last().unwrap().clone()).clone()
-        };
         // if there is morphism we need to check if it commutes i.e
         // FS -> H -> powerset and FS -> power_set
         let commutation_result = self.category.morphism_commute(
@@ -255,7 +803,66 @@ where
         }
     }
 
-    pub async fn is_consistent(&mut self) -> Result<Consistent<BaseCategory::Object>, CalfErrors> {
+    pub async fn build_or_get_prefix_alphabet_to_hypothesis(&mut self) -> Result<Arc<Morphism<CategorySubObjectAlias<BaseCategory>>>, CalfErrors>
+    {
+        /*
+        returns the morphism closeW: FS -> H, creating it if it does not exist yet.
+
+        FS ---------- closeW ----------> H
+         \                               |
+          \                              | m (monic)
+           \                             |
+            ----------------------------> 2^E
+
+        each object in FS is mapped to the object in H that has the same row in 2^E.
+        errors with HypothesisNotClosed naming an object of FS whose row is not in H.
+         */
+        let powerset_morphisms =
+            self.get_or_create_morphism_to_powerset().await?;
+        let prefix_to_powerset = powerset_morphisms.0.clone();
+        let prefix_alphabet_to_power_set = powerset_morphisms.1.clone();
+
+        let morphism_factors = self.category.morphism_factors(&*prefix_to_powerset)
+            .map_err(Self::factorization_failed(&prefix_to_powerset))?;
+        let epic_morphism = morphism_factors.0.clone();
+        let monic_morphism = morphism_factors.1.clone();
+
+        let prefix_alphabet_to_h_homset = self.category.get_hom_set(&*self.prefix_alphabet,
+                                                      &**epic_morphism.target_object()).await?;
+
+        match single_morphism(prefix_alphabet_to_h_homset, CalfErrors::NoMorphismFromFStoH, CalfErrors::MultipleMorphismsFromFSToH) {
+            Ok(prefix_alphabet_to_h) => return Ok(prefix_alphabet_to_h.clone()),
+            // built below
+            Err(CalfErrors::NoMorphismFromFStoH) => {},
+            Err(error) => return Err(error),
+        }
+
+        // since from H to powerset is monic
+        // our mapping will map each obect in FS to object in H such that H maps to powerset
+        let mut prefix_alphabet_to_h_mapping = HashMap::new();
+
+        let monic_powerset_reverse_mapping: HashMap<_,_> = monic_morphism.arrow_mappings().into_iter().flatten()
+            .map(|(source, target)| (target.clone(), source.clone())).collect();
+        for (source_morphism, target_morphism) in prefix_alphabet_to_power_set.arrow_mappings().into_iter().flatten() {
+            // get morphism in monic morphism that maps to the target morphism
+            if let Some(h_source_morphism) = monic_powerset_reverse_mapping.get(target_morphism) {
+                prefix_alphabet_to_h_mapping.insert(source_morphism.clone(), h_source_morphism.clone());
+            }
+            else{
+                return Err(CalfErrors::HypothesisNotClosed(source_morphism.source_object().category_id().to_string()));
+            }
+        }
+
+        let morphism = Arc::new(Morphism::new_with_mappings(
+            self.prefix_alphabet.clone(),
+            epic_morphism.target_object().clone(),
+            prefix_alphabet_to_h_mapping
+        ));
+        self.category.add_morphism(morphism.clone()).await?;
+        Ok(morphism)
+    }
+
+    pub async fn is_consistent(&mut self) -> Result<Consistent, CalfErrors> {
         /*
         checks if the wrapper is consistent with the oracle
         i.e. for every (s,a) ∈ FS, there exists s′ ∈ S such that:
@@ -280,17 +887,10 @@ where
         let prefix_alphabet_to_power_set = powerset_morphisms.1.clone();
 
         // prefix alphabet to hypothesis prefix alphabet
-        let fs_to_fh_morphisms =
-            self.category.get_hom_set(&*self.prefix_alphabet, &*self.hypothesis_prefix_alphabet).await?;
-        if fs_to_fh_morphisms.len() > 1 {
-            return Err(CalfErrors::MultipleMorphismsFromFStoFH);
-        }
-        if fs_to_fh_morphisms.is_empty() {
-            return Err(CalfErrors::NoMorphismFromFStoFH);
-        }
-
-        let fs_to_fh =
-            fs_to_fh_morphisms.into_iter().last().unwrap().clone();
+        let fs_to_fh = single_morphism(
+            self.category.get_hom_set(&*self.prefix_alphabet, &*self.hypothesis_prefix_alphabet).await?,
+            CalfErrors::NoMorphismFromFStoFH,
+            CalfErrors::MultipleMorphismsFromFStoFH)?.clone();
 
         // now we need to check if there is a morphism from FH to 2^E
         let fh_to_powerset_morphisms =
@@ -319,7 +919,7 @@ where
                     if let Some(existing_mapping) = fh_to_powerset_mapping.get(*fh_morphism) {
                         // if it is already mapped, then check if it maps to the same target
                         if existing_mapping != target_morphism {
-                            return Ok(Consistent::NotConsistent(HashSet::from_iter([source_morphism.clone()])));
+                            return Ok(Consistent::NotConsistent(self.distinguishing_suffixes().await?));
                         }
                         // if it maps to the same target, then continue
                         continue;
@@ -342,7 +942,10 @@ where
             self.category.add_morphism(morphism.clone()).await?;
             morphism.clone()
         } else {
-            (*fh_to_powerset_morphisms.iter().last().unwrap().clone()).clone()
+            single_morphism(
+                fh_to_powerset_morphisms,
+                CalfErrors::NoMorphismFromFHtoPowerset,
+                CalfErrors::MultipleMorphismsFromFHtoPowerset)?.clone()
         };
 
         // if there is morphism we need to check if it commutes i.e
@@ -350,17 +953,50 @@ where
         let commutation_result = self.category.morphism_commute(
             vec![&fs_to_fh, &fh_to_powerset],
             vec![&prefix_alphabet_to_power_set]).await?;
-        // match commutation_result {
-        //     MorphismCommutationResult::Commutative => {
-        //         // if it commutes, then we have a consistent wrapper
-        //         return Ok(Consistent::Consistent);
-        //     },
-        //     MorphismCommutationResult::NonCommutative(non_commuting_morphisms) => {
-        //         // if it does not commute, then we have a not consistent wrapper
-        //         return Ok(Consistent::NotConsistent(non_commuting_morphisms));
-        //     },
-        // }
-        Ok(Consistent::Consistent)
+        match commutation_result {
+            MorphismCommutationResult::Commutative => {
+                // if it commutes, then we have a consistent wrapper
+                Ok(Consistent::Consistent)
+            },
+            MorphismCommutationResult::NonCommutative(_) => {
+                // if it does not commute, then we have a not consistent wrapper
+                Ok(Consistent::NotConsistent(self.distinguishing_suffixes().await?))
+            },
+        }
+    }
+
+    async fn distinguishing_suffixes(&self) -> Result<Vec<String>, CalfErrors> {
+        /*
+        the non commuting morphisms name the rows of FS that disagree, but the suffix to add is read
+        off the table: for s1, s2 in S with equal rows and a symbol a such that the rows of s1.a and
+        s2.a differ in the column of e, a.e tells s1 and s2 apart.
+         */
+        let suffixes = self.suffix_columns().await?;
+        let upper_rows = self.observation_rows(&self.prefix).await?;
+        let lower_rows = self.observation_rows(&self.prefix_alphabet).await?;
+        let mut symbols = self.alphabet_symbols().await?;
+        symbols.sort();
+        let mut prefixes: Vec<&String> = upper_rows.keys().collect();
+        prefixes.sort();
+
+        let extended_row = |word: &str, symbol: &str| {
+            let extended_word = format!("{}{}", word, symbol);
+            lower_rows.get(&extended_word).or_else(|| upper_rows.get(&extended_word))
+                .map(String::as_str).map(parse_row)
+                .ok_or(CalfErrors::UnknownState(extended_word))
+        };
+        for (i, first) in prefixes.iter().enumerate() {
+            for second in prefixes.iter().skip(i + 1).filter(|second| upper_rows[**second] == upper_rows[*first]) {
+                for symbol in &symbols {
+                    let first_row = extended_row(first, symbol)?;
+                    let second_row = extended_row(second, symbol)?;
+                    if let Some(column) = (0..suffixes.len()).find(|&column| first_row.get(column) != second_row.get(column)) {
+                        return Ok(vec![format!("{}{}", symbol, suffixes[column])]);
+                    }
+                }
+            }
+        }
+        Err(CalfErrors::HypothesisNotConsistent("no suffix distinguishes the disagreeing rows".to_string()))
     }
 
 
@@ -376,34 +1012,28 @@ where
           morphism from FH to H that makes the two triangles commute.
 
          */
-        let fs_to_fh_morphisms =
-            self.category.get_hom_set(&*self.prefix_alphabet, &*self.hypothesis_prefix_alphabet).await?;
-        if fs_to_fh_morphisms.len() != 1 {
-            return Err(CalfErrors::MultipleMorphismsFromFStoFH);
-        }
-        let fs_to_fh =
-            fs_to_fh_morphisms.into_iter().last().unwrap().clone();
+        let fs_to_fh = single_morphism(
+            self.category.get_hom_set(&*self.prefix_alphabet, &*self.hypothesis_prefix_alphabet).await?,
+            CalfErrors::NoMorphismFromFStoFH,
+            CalfErrors::MultipleMorphismsFromFStoFH)?.clone();
 
-
-        let fh_to_powerset_morphisms =
-            self.category.get_hom_set(&*self.hypothesis_prefix_alphabet, &*self.suffix_power_set).await?;
-        if fh_to_powerset_morphisms.len() != 1 {
-            return Err(CalfErrors::MultipleMorphismsFromFHtoPowerset);
-        }
-        let fh_to_powerset = fh_to_powerset_morphisms.into_iter().last().unwrap().clone();
+        let fh_to_powerset = single_morphism(
+            self.category.get_hom_set(&*self.hypothesis_prefix_alphabet, &*self.suffix_power_set).await?,
+            CalfErrors::NoMorphismFromFHtoPowerset,
+            CalfErrors::MultipleMorphismsFromFHtoPowerset)?.clone();
 
         // get epic and monic morphisms from prefix to powerset
         let powerset_morphism = self.get_or_create_prefix_to_powerset_morphism().await?.clone();
-        let morphism_factors = self.category.morphism_factors(&*powerset_morphism)?;
+        let morphism_factors = self.category.morphism_factors(&*powerset_morphism)
+            .map_err(Self::factorization_failed(&powerset_morphism))?;
         let monic_morphism = morphism_factors.1.clone();
         let monic_morphism_mapping = monic_morphism.arrow_mappings().into_iter().flatten().collect::<HashMap<_,_>>();
         let hypothesis = monic_morphism.source_object().clone();
 
-        let fs_to_h = self.category.get_hom_set(&*self.prefix_alphabet, &*hypothesis).await?;
-        if fs_to_h.len() != 1 {
-            return Err(CalfErrors::MultipleMorphismsFromFStoH);
-        }
-        let fs_to_h = fs_to_h.into_iter().last().unwrap().clone();
+        let fs_to_h = single_morphism(
+            self.category.get_hom_set(&*self.prefix_alphabet, &*hypothesis).await?,
+            CalfErrors::NoMorphismFromFStoH,
+            CalfErrors::MultipleMorphismsFromFStoH)?.clone();
 
 
         // now we need to find a morphism from FH to H that makes the two triangles commute.
@@ -448,6 +1078,23 @@ where
             }
         }
 
+        // a transition function added by an earlier call is reused, any other FH -> H morphism
+        // would be a second transition function for the same hypothesis
+        let existing: Vec<_> = self.category.get_hom_set(&*self.hypothesis_prefix_alphabet, &*hypothesis).await?
+            .into_iter().collect();
+        if existing.len() > 1 {
+            return Err(CalfErrors::MultipleMorphismsFromFHtoH);
+        }
+        if let Some(existing_morphism) = existing.first() {
+            let existing_mappings: HashMap<_, _> = existing_morphism.arrow_mappings().into_iter().flatten()
+                .map(|(source, target)| (source.clone(), target.clone()))
+                .collect();
+            if existing_mappings != fh_to_h_mappings {
+                return Err(CalfErrors::MultipleMorphismsFromFHtoH);
+            }
+            return Ok((*existing_morphism).clone());
+        }
+
         let new_morphism = Arc::new(BaseCategory::Morphism::new_with_mappings(
             self.hypothesis_prefix_alphabet.clone(),
             hypothesis.clone(),
@@ -462,14 +1109,16 @@ where
         let prefix_to_powerset_morphism=
             self.get_or_create_prefix_to_powerset_morphism().await?.clone();
         let morphism_factors =
-            self.category.morphism_factors(&*prefix_to_powerset_morphism)?;
+            self.category.morphism_factors(&*prefix_to_powerset_morphism)
+                .map_err(Self::factorization_failed(&prefix_to_powerset_morphism))?;
         // epic morphism from S (prefix) to H (hypothesis)
         let epic_morphism = morphism_factors.0.clone();
 
         let (product_object, product_mapping) = apply_product(
             &mut self.category,
             &self.prefix,
-            self.alphabets.clone()).await.expect("Failed to apply");
+            self.alphabets.clone()).await
+            .map_err(CalfErrors::ProductConstructionFailed)?;
 
         self.prefix_alphabet = product_object;
 
@@ -484,18 +1133,27 @@ where
     }
 
     async fn create_suffix_power_set(&mut self) -> Result<(), CalfErrors> {
-        // create all possible 2^E
-        let mut power_set = BaseCategory::Object::new().await?;
-        let n = self.suffix.get_all_objects().await?.len();
-
-        for i in 0..(1 << n) {
-            // add each element to the power set
-            let mut row = "".to_string();
-            for j in 0..n {
-                let value: bool = (i & (1 << j)) != 0;
-                row += &value.to_string();
+        /*
+        only the rows S and S.A map to are needed in 2^E: H is the image of S, FH is read off
+        S.A, and nothing else has a morphism into 2^E. so instead of all 2^n rows for n
+        suffixes, 2^E holds the rows of the table, at most |S| * (|A| + 1) of them.
+         */
+        let suffixes = self.suffix_columns().await?;
+        let symbols = self.alphabet_symbols().await?;
+        let prefixes: Vec<String> = self.prefix.get_all_objects().await?.into_iter()
+            .map(|prefix| prefix.category_id().to_string())
+            .collect();
+
+        let mut rows = HashSet::new();
+        for prefix in &prefixes {
+            rows.insert(self.row(prefix, &suffixes).await?);
+            for symbol in &symbols {
+                rows.insert(self.row(&format!("{}{}", prefix, symbol), &suffixes).await?);
             }
+        }
 
+        let mut power_set = BaseCategory::Object::new().await?;
+        for row in rows {
             power_set.add_object(Arc::new(<BaseCategory::Object as CategoryTrait>::Object::from(row))).await?;
         }
         let power_set = Arc::new(power_set);
@@ -529,11 +1187,11 @@ where
             }
 
             // here we have exactly one morphism from fs to power set and one from suffix to power set
-            if prefix_to_power_set.len() == 1 {
-                let morphism = prefix_to_power_set.into_iter().last().unwrap().clone();
-                return Ok(morphism)
-            }
-            return Err(CalfErrors::UnknownError);
+            let morphism = single_morphism(
+                prefix_to_power_set,
+                CalfErrors::UnknownError,
+                CalfErrors::MultipleMorphismsFromSuffixToPowerSet)?.clone();
+            return Ok(morphism)
         }
     }
 
@@ -560,11 +1218,10 @@ where
             }
 
             // here we have exactly one morphism from fs to power set and one from suffix to power set
-            if prefix_alphabet_to_power_set.len() == 1 {
-                return Ok(prefix_alphabet_to_power_set.into_iter().last().unwrap().clone())
-            }
-
-            return Err(CalfErrors::UnknownError);
+            return Ok(single_morphism(
+                prefix_alphabet_to_power_set,
+                CalfErrors::UnknownError,
+                CalfErrors::MultipleMorphismsFromFSToPowerSet)?.clone())
         }
     }
 
@@ -580,29 +1237,135 @@ where
     }
 
 
+    pub async fn explain_states(&mut self, state_a: &str, state_b: &str) -> Result<Option<(String, bool, bool)>, CalfErrors>
+    {
+        // states are given by their access strings in S. returns the first suffix in E on which
+        // their rows differ together with the value of each row, or None if the rows are equal.
+        self.get_or_create_prefix_to_powerset_morphism().await?;
+        let rows = self.observation_rows(&self.prefix).await?;
+        let row_a = rows.get(state_a).ok_or_else(|| CalfErrors::UnknownState(state_a.to_string()))?;
+        let row_b = rows.get(state_b).ok_or_else(|| CalfErrors::UnknownState(state_b.to_string()))?;
+
+        let suffixes = self.suffix_columns().await?;
+        let difference = suffixes.into_iter()
+            .zip(parse_row(row_a).into_iter().zip(parse_row(row_b)))
+            .find(|(_, (value_a, value_b))| value_a != value_b)
+            .map(|(suffix, (value_a, value_b))| (suffix, value_a, value_b));
+        Ok(difference)
+    }
+
+    pub async fn export_partition(&mut self) -> Result<Vec<Vec<String>>, CalfErrors>
+    {
+        // groups the words in S by their row over E, each group is a candidate state
+        self.get_or_create_prefix_to_powerset_morphism().await?;
+        let rows = self.observation_rows(&self.prefix).await?;
+
+        let mut classes: HashMap<String, Vec<String>> = HashMap::new();
+        for (word, row) in rows {
+            classes.entry(row).or_default().push(word);
+        }
+        let mut partition: Vec<Vec<String>> = classes.into_values()
+            .map(|mut class| {
+                class.sort();
+                class
+            })
+            .collect();
+        partition.sort();
+        Ok(partition)
+    }
+
+    async fn suffix_columns(&self) -> Result<Vec<String>, CalfErrors>
+    {
+        // the suffixes in E in the order their membership results appear in a row
+        let mut suffixes: Vec<String> = self.suffix.get_all_objects().await?.into_iter()
+            .map(|suffix| suffix.category_id().to_string())
+            .collect();
+        suffixes.sort();
+        Ok(suffixes)
+    }
+
+    async fn observation_rows(&self, object: &Arc<BaseCategory::Object>) -> Result<HashMap<String, String>, CalfErrors>
+    {
+        // reads the row in 2^E of every object in `object` without issuing membership queries
+        let morphism = single_morphism(
+            self.category.get_hom_set(&**object, &*self.suffix_power_set).await?,
+            CalfErrors::NoMorphismToPowerSet,
+            CalfErrors::MultipleMorphismsToPowerSet)?.clone();
+
+        let rows = morphism.arrow_mappings().into_iter().flatten()
+            .map(|(source, target)| (
+                source.source_object().category_id().to_string(),
+                target.source_object().category_id().to_string()))
+            .collect();
+        Ok(rows)
+    }
+
+    async fn membership_query(&mut self, query: &str) -> bool
+    {
+        if !self.cache_membership {
+            self.stats.membership_queries += 1;
+            return AsyncOracleTrait::membership_query(&self.oracle, &query.to_string()).await;
+        }
+        if let Some(result) = self.membership_cache.get(query) {
+            self.cache_hits += 1;
+            return *result;
+        }
+        self.cache_misses += 1;
+        self.stats.membership_queries += 1;
+        let result = AsyncOracleTrait::membership_query(&self.oracle, &query.to_string()).await;
+        self.membership_cache.insert(query.to_string(), result);
+        result
+    }
+
+    async fn row(&mut self, word: &str, suffixes: &[String]) -> Result<String, CalfErrors>
+    {
+        // the membership results of the word followed by each suffix e.g. "truefalse"
+        let mut row = String::new();
+        for suffix in suffixes {
+            let query = self.query_word(&[word, suffix]).await?;
+            row += &self.membership_query(&query).await.to_string();
+        }
+        Ok(row)
+    }
+
+    pub fn cache_stats(&self) -> (usize, usize)
+    {
+        // (hits, misses) of the membership query cache
+        (self.cache_hits, self.cache_misses)
+    }
+
+    pub fn stats(&self) -> LearningStats
+    {
+        self.stats
+    }
+
     pub async fn add_power_set_morphism(&mut self, object: &Arc<BaseCategory::Object>) -> Result<(), CalfErrors>
     {
         let mut mappings = HashMap::new();
 
-        let suffix_objects = self.suffix.get_all_objects().await?;
-        // map identity morphism first.
+        let suffix_objects = self.suffix_columns().await?;
+        // the morphism is given by the identity morphisms of the words, each sent to the one of
+        // its row. every word of S, S.A and E and every row of 2^E is added with add_object,
+        // which registers its identity morphism with it, and 2^E holds the row of every word of
+        // S and S.A, so both lookups succeed for objects of the table and fail with the object
+        // named otherwise.
         for sub_object in object.get_all_objects().await? {
-            let mut oracle_object = "".to_string();
-            for suffix in &suffix_objects{
-                let query = sub_object.category_id().to_owned() + suffix.category_id().clone();
-                let query_result = self.oracle.membership_query(&query.to_string());
-                oracle_object += &query_result.to_string();
-            }
+            let oracle_object = self.row(&sub_object.category_id().to_string(), &suffix_objects).await?;
             // now find target object oracle object.
-            println!("Mapping object {} to {}", sub_object.category_id(), oracle_object);
-            println!("Suffix object");
-            for s in self.suffix_power_set.get_all_objects().await?{
-                println!(" - {}", s.category_id());
+            if self.verbose {
+                self.log(format_args!("Mapping object {} to {}", sub_object.category_id(), oracle_object));
+                self.log(format_args!("Suffix object"));
+                for s in self.suffix_power_set.get_all_objects().await?{
+                    self.log(format_args!(" - {}", s.category_id()));
+                }
             }
             let target_object = self.suffix_power_set.get_object(&<String as Into<<BaseCategory::Object as CategoryTrait>::Object>>::into(oracle_object)).await?;
-            let target_identity_morphism = self.suffix_power_set.get_identity_morphism(&**target_object).await?;
+            let target_identity_morphism = self.suffix_power_set.get_identity_morphism(&**target_object).await
+                .map_err(Self::missing_identity(&target_object.category_id().to_string()))?;
+            let source_identity_morphism = object.get_identity_morphism(&**sub_object).await
+                .map_err(Self::missing_identity(&sub_object.category_id().to_string()))?;
             mappings.insert(
-                object.get_identity_morphism(&**sub_object).await?.clone(),
+                source_identity_morphism.clone(),
                 target_identity_morphism.clone()
             );
         }
@@ -617,6 +1380,97 @@ where
         Ok(())
     }
 
+    pub async fn add_prefix(&mut self, prefix: &str) -> Result<(), CalfErrors>
+    {
+        // S is kept prefix closed, so every prefix of the word is added with it.
+        // this only extends the table, closedness and consistency are left to the caller.
+        let symbols = self.split_query(prefix).await?;
+        let words = (1..=symbols.len()).map(|i| symbols[..i].concat()).collect();
+
+        if let Some(new_prefix) = self.insert_words(&self.prefix.clone(), words).await? {
+            self.prefix = new_prefix;
+            self.ensure_empty_word().await?;
+            self.fill_table().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn add_suffix(&mut self, suffix: &str) -> Result<(), CalfErrors>
+    {
+        // E is kept suffix closed, so every suffix of the word is added with it.
+        let symbols = self.split_query(suffix).await?;
+        let words = (0..symbols.len()).map(|i| symbols[i..].concat()).collect();
+
+        if let Some(new_suffix) = self.insert_words(&self.suffix.clone(), words).await? {
+            self.suffix = new_suffix;
+            self.ensure_empty_word().await?;
+            self.fill_table().await?;
+        }
+        Ok(())
+    }
+
+    async fn alphabet_symbols(&self) -> Result<Vec<String>, CalfErrors>
+    {
+        Ok(self.alphabets.get_all_objects().await?.into_iter()
+            .map(|symbol| symbol.category_id().to_string())
+            .filter(|symbol| !symbol.is_empty())
+            .collect())
+    }
+
+    async fn query_word(&self, words: &[&str]) -> Result<String, CalfErrors>
+    {
+        // words in the table are plain concatenations of symbols, the oracle gets them joined by the separator
+        let mut symbols = vec![];
+        for word in words {
+            symbols.extend(self.split_word(word).await?);
+        }
+        Ok(symbols.join(&self.symbol_separator))
+    }
+
+    async fn split_query(&self, word: &str) -> Result<Vec<String>, CalfErrors>
+    {
+        // splits a word in the oracle's format i.e. with symbols joined by the separator
+        if self.symbol_separator.is_empty() {
+            return self.split_word(word).await;
+        }
+        let alphabet = self.alphabet_symbols().await?;
+        word.split(self.symbol_separator.as_str())
+            .filter(|symbol| !symbol.is_empty())
+            .map(|symbol| match alphabet.iter().any(|known| known == symbol) {
+                true => Ok(symbol.to_string()),
+                false => Err(CalfErrors::InvalidWord(word.to_string())),
+            })
+            .collect()
+    }
+
+    async fn split_word(&self, word: &str) -> Result<Vec<String>, CalfErrors>
+    {
+        // splits a word of the table into alphabet symbols. the alphabet is checked to be uniquely
+        // decodable on creation, so there is at most one way to do it.
+        let symbols = self.alphabet_symbols().await?;
+        split_into_symbols(word, &symbols).ok_or_else(|| CalfErrors::InvalidWord(word.to_string()))
+    }
+
+    async fn insert_words(
+        &mut self,
+        target_ref: &Arc<BaseCategory::Object>,
+        words: Vec<String>,
+    ) -> Result<Option<Arc<BaseCategory::Object>>, CalfErrors>
+    {
+        // returns the extended object, or None if all the words were already in target_ref
+        let mut objects = vec![];
+        for word in words {
+            let object = <BaseCategory::Object as CategoryTrait>::Object::from(word);
+            if target_ref.get_object(&object).await.is_err() {
+                objects.push(Arc::new(object));
+            }
+        }
+        if objects.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(self.extend_object(target_ref, objects).await?))
+    }
+
     pub async fn update_table(
         &mut self,
         target_ref: &Arc<BaseCategory::Object>,
@@ -628,6 +1482,15 @@ where
             objects.push(new_object);
         }
 
+        self.extend_object(target_ref, objects).await
+    }
+
+    async fn extend_object(
+        &mut self,
+        target_ref: &Arc<BaseCategory::Object>,
+        objects: Vec<Arc<<BaseCategory::Object as CategoryTrait>::Object>>,
+    ) -> Result<Arc<BaseCategory::Object>, CalfErrors>{
+        // creates a new object containing target_ref and objects, linked to target_ref by an inclusion
         let inclusion_functor = inclusion_functor(
             target_ref.clone(), objects).await?;
 
@@ -646,6 +1509,189 @@ where
         Ok(new_data.clone())
     }
 
+    async fn contains_empty_word(object: &Arc<BaseCategory::Object>) -> bool {
+        object.get_object(&<BaseCategory::Object as CategoryTrait>::Object::from("")).await.is_ok()
+    }
+
+    async fn ensure_empty_word(&mut self) -> Result<(), CalfErrors> {
+        // L* requires the empty word to always be in both S and E.
+        // callers are responsible for rebuilding the objects derived from the one they mutated.
+        if !Self::contains_empty_word(&self.prefix).await {
+            let empty_word = Arc::new(<BaseCategory::Object as CategoryTrait>::Object::from(""));
+            self.prefix = self.extend_object(&self.prefix.clone(), vec![empty_word]).await?;
+        }
+        if !Self::contains_empty_word(&self.suffix).await {
+            let empty_word = Arc::new(<BaseCategory::Object as CategoryTrait>::Object::from(""));
+            self.suffix = self.extend_object(&self.suffix.clone(), vec![empty_word]).await?;
+        }
+        Ok(())
+    }
+
+
+    pub async fn category_object_count(&self) -> Result<usize, CalfErrors> {
+        // every table update adds objects to the category, useful to monitor growth on long runs
+        Ok(self.category.get_all_objects().await?.len())
+    }
+
+    pub async fn export_observation_table_json(&self, file_path: &str) -> Result<(), CalfErrors> {
+        /*
+        writes the observation table as json:
+            version: schema version
+            suffixes: E in column order
+            upper_rows: S
+            lower_rows: FS
+            cells: row of each word in S and FS, one bool per suffix
+         */
+        let suffixes = self.suffix_columns().await?;
+        let upper_rows = self.observation_rows(&self.prefix).await?;
+        let lower_rows = self.observation_rows(&self.prefix_alphabet).await?;
+
+        let mut upper_words: Vec<&String> = upper_rows.keys().collect();
+        upper_words.sort();
+        let mut lower_words: Vec<&String> = lower_rows.keys().collect();
+        lower_words.sort();
+
+        let cells: serde_json::Map<String, serde_json::Value> = upper_rows.iter().chain(lower_rows.iter())
+            .map(|(word, row)| (word.clone(), serde_json::json!(parse_row(row))))
+            .collect();
+
+        let table = serde_json::json!({
+            "version": OBSERVATION_TABLE_SCHEMA_VERSION,
+            "suffixes": suffixes,
+            "upper_rows": upper_words,
+            "lower_rows": lower_words,
+            "cells": cells,
+        });
+        let contents = serde_json::to_string_pretty(&table)
+            .map_err(|error| CalfErrors::ExportError(error.to_string()))?;
+        std::fs::write(file_path, contents)
+            .map_err(|error| CalfErrors::ExportError(error.to_string()))
+    }
+
+    pub async fn export_dot(&self, file_path: &str) -> Result<(), CalfErrors> {
+        // writes the learned automaton rather than the whole category
+        let automaton = self.extract_automaton().await?;
+        std::fs::write(file_path, automaton.to_dot())
+            .map_err(|error| CalfErrors::ExportError(error.to_string()))
+    }
+
+    pub async fn export_dot_with_trace(&self, word: &str, file_path: &str) -> Result<(), CalfErrors> {
+        // as export_dot, with the path of the word, given in the oracle's format, highlighted
+        let symbols = self.split_query(word).await?;
+        let automaton = self.extract_automaton().await?;
+        std::fs::write(file_path, automaton.to_dot_with_trace(&symbols))
+            .map_err(|error| CalfErrors::ExportError(error.to_string()))
+    }
+
+    pub async fn save_session(&self, file_path: &str) -> Result<(), CalfErrors> {
+        /*
+        writes what is needed to resume the run as json:
+            version: schema version
+            alphabet, symbol_separator, counterexample_strategy, max_counterexample_length,
+            cache_membership, export_path, verbose: the configuration of the run
+            prefixes, suffixes: S and E
+            membership_cache: every answer of the oracle so far
+
+        the rest of the table (FS, FH, 2^E and their morphisms) is derived from S and E,
+        so the category itself is not saved. neither is the log writer, a loaded session writes
+        its progress to stdout until set_log_writer is called.
+         */
+        let mut prefixes: Vec<String> = self.prefix.get_all_objects().await?.into_iter()
+            .map(|word| word.category_id().to_string())
+            .collect();
+        prefixes.sort();
+        let mut suffixes: Vec<String> = self.suffix.get_all_objects().await?.into_iter()
+            .map(|word| word.category_id().to_string())
+            .collect();
+        suffixes.sort();
+
+        let session = serde_json::json!({
+            "version": SESSION_SCHEMA_VERSION,
+            "alphabet": self.alphabet_symbols().await?,
+            "symbol_separator": self.symbol_separator,
+            "counterexample_strategy": format!("{:?}", self.counterexample_strategy),
+            "max_counterexample_length": self.max_counterexample_length,
+            "cache_membership": self.cache_membership,
+            "export_path": self.export_path,
+            "verbose": self.verbose,
+            "prefixes": prefixes,
+            "suffixes": suffixes,
+            "membership_cache": self.membership_cache,
+        });
+        let contents = serde_json::to_string_pretty(&session)
+            .map_err(|error| CalfErrors::SessionError(error.to_string()))?;
+        std::fs::write(file_path, contents)
+            .map_err(|error| CalfErrors::SessionError(error.to_string()))
+    }
+
+    pub async fn load_session(file_path: &str, oracle: Oracle) -> Result<Self, CalfErrors> {
+        // rebuilds the table from a file written by save_session. the configuration and the cache
+        // are restored before the table is filled, so rebuilding asks the oracle only what was not
+        // asked before the save, in the same format. settings missing from the file keep their defaults.
+        let contents = std::fs::read_to_string(file_path)
+            .map_err(|error| CalfErrors::SessionError(error.to_string()))?;
+        let session: serde_json::Value = serde_json::from_str(&contents)
+            .map_err(|error| CalfErrors::SessionError(error.to_string()))?;
+        if session["version"] != SESSION_SCHEMA_VERSION {
+            return Err(CalfErrors::SessionError(format!("unsupported session version {}", session["version"])));
+        }
+
+        let alphabet = session_strings(&session, "alphabet")?;
+        check_distinct_symbols(&alphabet)?;
+        let alphabets = BaseCategory::Object::from_objects(
+            alphabet.iter().map(String::as_str).collect()).await?;
+        let mut calf = Self::with_empty_table(Arc::new(alphabets), oracle).await?;
+        calf.symbol_separator = session["symbol_separator"].as_str().unwrap_or_default().to_string();
+        calf.counterexample_strategy = match session["counterexample_strategy"].as_str() {
+            Some("AllPrefixes") => CounterexampleStrategy::AllPrefixes,
+            Some("RivestSchapire") | None => CounterexampleStrategy::RivestSchapire,
+            Some(strategy) => return Err(CalfErrors::SessionError(format!("unknown counterexample strategy {}", strategy))),
+        };
+        calf.max_counterexample_length = session["max_counterexample_length"].as_u64().map(|length| length as usize);
+        calf.cache_membership = session["cache_membership"].as_bool().unwrap_or(true);
+        calf.export_path = session["export_path"].as_str().map(String::from);
+        calf.verbose = session["verbose"].as_bool().unwrap_or(false);
+        calf.membership_cache = serde_json::from_value(session["membership_cache"].clone())
+            .map_err(|error| CalfErrors::SessionError(error.to_string()))?;
+
+        let prefixes = calf.session_words(&session, "prefixes").await?;
+        if let Some(prefix) = calf.insert_words(&calf.prefix.clone(), prefixes).await? {
+            calf.prefix = prefix;
+        }
+        let suffixes = calf.session_words(&session, "suffixes").await?;
+        if let Some(suffix) = calf.insert_words(&calf.suffix.clone(), suffixes).await? {
+            calf.suffix = suffix;
+        }
+        calf.ensure_empty_word().await?;
+        calf.fill_table().await?;
+        Ok(calf)
+    }
+
+    async fn session_words(&self, session: &serde_json::Value, key: &str) -> Result<Vec<String>, CalfErrors> {
+        // words of S and E have to be spelled by the alphabet of the session
+        let words = session_strings(session, key)?;
+        for word in &words {
+            self.split_word(word).await
+                .map_err(|_| CalfErrors::SessionError(format!("{} has {} which is not a word over the alphabet", key, word)))?;
+        }
+        Ok(words)
+    }
+
+    fn describe_morphism(morphism: &Morphism<CategorySubObjectAlias<BaseCategory>>) -> String {
+        // used in error messages, morphisms are identified by their source and target objects
+        format!("{} -> {}", morphism.source_object().category_id(), morphism.target_object().category_id())
+    }
+
+    fn factorization_failed(morphism: &Morphism<CategorySubObjectAlias<BaseCategory>>) -> impl FnOnce(Errors) -> CalfErrors {
+        let description = Self::describe_morphism(morphism);
+        move |error| CalfErrors::FactorizationFailed(description, error)
+    }
+
+    fn missing_identity(object_id: &str) -> impl FnOnce(Errors) -> CalfErrors {
+        let object_id = object_id.to_string();
+        move |error| CalfErrors::MissingIdentityMorphism(object_id, error)
+    }
+
 
     pub async fn export_cytoscape(&self, file_path: &str) -> Result<(), CalfErrors> {
         Ok(save_category_to_cytoscape_json_file(
@@ -658,3 +1704,812 @@ where
 }
 
 
+
+// configures and creates a CALF from plain symbols, e.g.
+//     CalfBuilder::new(oracle).alphabet(vec!["a", "b"]).build().await
+pub struct CalfBuilder<Oracle: AsyncOracleTrait<String>, BaseCategory> {
+    oracle: Oracle,
+    alphabet: Vec<String>,
+    max_counterexample_length: Option<usize>,
+    cache_membership: bool,
+    symbol_separator: String,
+    export_path: Option<String>,
+    _category: PhantomData<BaseCategory>,
+}
+
+
+impl <Oracle, BaseCategory> CalfBuilder<Oracle, BaseCategory>
+where
+    Oracle: AsyncOracleTrait<String>,
+    BaseCategory: CategoryTrait<Morphism = Arrow<<BaseCategory as CategoryTrait>::Object, <BaseCategory as CategoryTrait>::Object>> + Hash + Eq + Clone ,
+    BaseCategory::Object: Clone + From<String> + CategoryCloneWithNewId,
+    <BaseCategory::Object as CategoryTrait>::Object : Clone + From<String> + for<'a> From<&'a str>,
+    <<BaseCategory::Object as CategoryTrait>::Object as CategoryTrait>::Object: Clone + From<String> + From<ObjectId>,
+{
+    pub fn new(oracle: Oracle) -> Self
+    {
+        CalfBuilder {
+            oracle,
+            alphabet: vec![],
+            max_counterexample_length: None,
+            cache_membership: true,
+            symbol_separator: String::new(),
+            export_path: None,
+            _category: PhantomData,
+        }
+    }
+
+    pub fn alphabet(mut self, alphabet: Vec<&str>) -> Self
+    {
+        self.alphabet = alphabet.into_iter().map(str::to_string).collect();
+        self
+    }
+
+    pub fn max_counterexample_length(mut self, max_counterexample_length: usize) -> Self
+    {
+        self.max_counterexample_length = Some(max_counterexample_length);
+        self
+    }
+
+    pub fn cache_membership(mut self, cache_membership: bool) -> Self
+    {
+        self.cache_membership = cache_membership;
+        self
+    }
+
+    pub fn symbol_separator(mut self, symbol_separator: &str) -> Self
+    {
+        self.symbol_separator = symbol_separator.to_string();
+        self
+    }
+
+    pub fn export_path(mut self, export_path: &str) -> Self
+    {
+        self.export_path = Some(export_path.to_string());
+        self
+    }
+
+    pub async fn build(self) -> Result<CALF<Oracle, BaseCategory>, CalfErrors>
+    {
+        if self.alphabet.is_empty() {
+            return Err(CalfErrors::EmptyAlphabet);
+        }
+        check_distinct_symbols(&self.alphabet)?;
+        let alphabets = BaseCategory::Object::from_objects(
+            self.alphabet.iter().map(String::as_str).collect()).await?;
+        let mut calf = CALF::with_empty_table(Arc::new(alphabets), self.oracle).await?;
+        calf.set_symbol_separator(&self.symbol_separator);
+        calf.set_membership_cache(self.cache_membership);
+        if let Some(max_counterexample_length) = self.max_counterexample_length {
+            calf.set_max_counterexample_length(max_counterexample_length);
+        }
+        if let Some(export_path) = &self.export_path {
+            calf.set_export_path(export_path);
+        }
+        calf.fill_table().await?;
+        Ok(calf)
+    }
+}
+
+fn check_distinct_symbols(symbols: &[String]) -> Result<(), CalfErrors> {
+    // the alphabet object is a set, so duplicates have to be caught before it is built
+    let mut seen = HashSet::new();
+    match symbols.iter().find(|symbol| !seen.insert(*symbol)) {
+        Some(symbol) => Err(CalfErrors::InvalidAlphabet(format!("duplicate symbol {}", symbol))),
+        None => Ok(()),
+    }
+}
+
+fn session_strings(session: &serde_json::Value, key: &str) -> Result<Vec<String>, CalfErrors> {
+    session[key].as_array()
+        .ok_or_else(|| CalfErrors::SessionError(format!("missing {}", key)))?
+        .iter()
+        .map(|value| value.as_str()
+            .map(str::to_string)
+            .ok_or_else(|| CalfErrors::SessionError(format!("{} has a non string entry", key))))
+        .collect()
+}
+
+
+/// Splits a word spelled without separators into symbols, `None` if it is not a word over them.
+/// For a uniquely decodable set of symbols, as CALF's alphabets are, the split is the only one.
+pub fn split_into_symbols(word: &str, symbols: &[String]) -> Option<Vec<String>> {
+    // previous[i] is the symbol ending a split of the first i bytes of the word.
+    // a greedy split is not enough e.g. abb over {a, ab, bb}
+    let mut previous: Vec<Option<&String>> = vec![None; word.len() + 1];
+    for start in 0..word.len() {
+        if start > 0 && previous[start].is_none() {
+            continue;
+        }
+        for symbol in symbols {
+            let end = start + symbol.len();
+            if previous.get(end) == Some(&None) && word[start..].starts_with(symbol.as_str()) {
+                previous[end] = Some(symbol);
+            }
+        }
+    }
+
+    let mut result = vec![];
+    let mut end = word.len();
+    while end > 0 {
+        let symbol = previous[end]?;
+        result.push(symbol.clone());
+        end -= symbol.len();
+    }
+    result.reverse();
+    Some(result)
+}
+
+
+fn uniquely_decodable(symbols: &[String]) -> bool {
+    /*
+    Sardinas-Patterson test. starting from the dangling suffixes of pairs of symbols where one is a
+    prefix of the other, keeps following dangling suffixes against the symbols. the code is
+    ambiguous iff a dangling suffix is itself a symbol.
+     */
+    let dangling = |prefix: &str, word: &str| word.strip_prefix(prefix)
+        .filter(|rest| !rest.is_empty())
+        .map(str::to_string);
+
+    let mut current: HashSet<String> = symbols.iter()
+        .flat_map(|u| symbols.iter().filter_map(move |v| dangling(u, v)))
+        .collect();
+    let mut seen = HashSet::new();
+    loop {
+        if current.iter().any(|word| symbols.contains(word)) {
+            return false;
+        }
+        current.retain(|word| seen.insert(word.clone()));
+        if current.is_empty() {
+            return true;
+        }
+        current = current.iter()
+            .flat_map(|word| symbols.iter().flat_map(move |symbol| {
+                dangling(word, symbol).into_iter().chain(dangling(symbol, word))
+            }))
+            .collect();
+    }
+}
+
+
+fn parse_row(row: &str) -> Vec<bool> {
+    // rows are the concatenated membership results of each suffix e.g. "truefalse"
+    let mut values = vec![];
+    let mut rest = row;
+    while !rest.is_empty() {
+        if let Some(remaining) = rest.strip_prefix("true") {
+            values.push(true);
+            rest = remaining;
+        } else if let Some(remaining) = rest.strip_prefix("false") {
+            values.push(false);
+            rest = remaining;
+        } else {
+            break;
+        }
+    }
+    values
+}
+
+
+fn single_morphism<T>(hom_set: impl IntoIterator<Item = T>, missing: CalfErrors, multiple: CalfErrors) -> Result<T, CalfErrors> {
+    // hom-sets are unordered, so when the design expects exactly one morphism
+    // anything else is an error rather than an arbitrary pick
+    let mut morphisms = hom_set.into_iter();
+    match (morphisms.next(), morphisms.next()) {
+        (Some(morphism), None) => Ok(morphism),
+        (None, _) => Err(missing),
+        (Some(_), Some(_)) => Err(multiple),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oracle_trait::AutomatonTrait;
+    use crate::test_support::*;
+
+    #[test]
+    fn rejects_alphabets_spelling_a_word_twice() {
+        assert!(!uniquely_decodable(&symbols(&["a", "b", "ab"])));
+        assert!(!uniquely_decodable(&symbols(&["a", "ab", "ba"])));
+        assert!(uniquely_decodable(&symbols(&["foo", "bar", "baz"])));
+        assert!(uniquely_decodable(&symbols(&["a", "ab", "bb"])));
+    }
+
+    #[test]
+    fn splits_words_where_the_longest_symbol_first_fails() {
+        let alphabet = symbols(&["a", "ab", "bb"]);
+        assert_eq!(split_into_symbols("abb", &alphabet), Some(symbols(&["a", "bb"])));
+        assert_eq!(split_into_symbols("", &alphabet), Some(vec![]));
+        assert_eq!(split_into_symbols("ba", &alphabet), None);
+    }
+
+    #[tokio::test]
+    async fn queries_multi_character_symbols_joined_by_the_separator() {
+        // push and pop alternate, starting with push
+        let oracle = PredicateOracle::new(|word| {
+            word.is_empty() || word.split(' ').enumerate()
+                .all(|(i, symbol)| symbol == if i % 2 == 0 { "push" } else { "pop" })
+        });
+        let queries = oracle.queries();
+        let mut calf = builder(vec!["push", "pop"], oracle).await
+            .symbol_separator(" ")
+            .build().await
+            .unwrap();
+        calf.run().await.unwrap();
+
+        let queries = queries.lock().unwrap().clone();
+        assert!(queries.iter().any(|query| query == "push pop"));
+        assert!(queries.iter().all(|query| query.is_empty()
+            || query.split(' ').all(|symbol| symbol == "push" || symbol == "pop")));
+        let dfa = calf.extract_automaton().await.unwrap();
+        assert!(dfa.accepts(&symbols(&["push", "pop", "push"])));
+        assert!(!dfa.accepts(&symbols(&["pop"])));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn learning_runs_on_a_spawned_task() {
+        // tokio::spawn needs the learner and the future of its run to be Send
+        let calf = learner(vec!["a", "b"], PredicateOracle::new(|word| word.ends_with('a'))).await;
+        let dfa = tokio::spawn(async move {
+            let mut calf = calf;
+            calf.run().await.unwrap();
+            calf.extract_automaton().await.unwrap()
+        }).await.unwrap();
+
+        assert_eq!(dfa.state_count(), Some(2));
+        assert!(dfa.accepts(&symbols(&["b", "a"])));
+        assert!(!dfa.accepts(&symbols(&["a", "b"])));
+    }
+
+    #[tokio::test]
+    async fn trivial_languages_are_reported_in_the_stats() {
+        for (accepts, trivial_language) in [(false, Some(TrivialLanguage::Empty)), (true, Some(TrivialLanguage::Universal))] {
+            let mut calf = learner(vec!["a", "b"], PredicateOracle::new(move |_| accepts)).await;
+            let log = SharedBuffer::default();
+            calf.set_log_writer(log.clone());
+            calf.set_verbose(true);
+            calf.run().await.unwrap();
+            assert_eq!(calf.stats().trivial_language, trivial_language);
+            assert!(String::from_utf8(log.0.lock().unwrap().clone()).unwrap().contains("Warning"));
+        }
+
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(|word| word.ends_with('a'))).await;
+        calf.run().await.unwrap();
+        assert_eq!(calf.stats().trivial_language, None);
+    }
+
+    #[tokio::test]
+    async fn unfactorizable_morphisms_keep_the_category_error() {
+        let mut calf = learner(vec!["a"], PredicateOracle::new(|_| true)).await;
+        // a fresh 2^E has no morphism from S yet, so is_closed takes one mapping no word of S
+        calf.create_suffix_power_set().await.unwrap();
+        let unfactorizable = Morphism::new_with_mappings(calf.prefix.clone(), calf.suffix_power_set.clone(), HashMap::new());
+        calf.category.add_morphism(Arc::new(unfactorizable)).await.unwrap();
+
+        let error = match calf.is_closed().await {
+            Err(error) => error,
+            Ok(_) => panic!("a morphism mapping no word of S was factorized"),
+        };
+        assert!(matches!(error, CalfErrors::FactorizationFailed(..)), "{error:?}");
+        assert!(std::error::Error::source(&error).is_some());
+    }
+
+    #[tokio::test]
+    async fn missing_identity_morphisms_keep_the_category_error() {
+        let calf = learner(vec!["a"], PredicateOracle::new(|_| true)).await;
+        let row = <<TestCategory as CategoryTrait>::Object as CategoryTrait>::Object::from("not a row".to_string());
+
+        let error = match calf.suffix_power_set.get_identity_morphism(&row).await
+            .map_err(CALF::<PredicateOracle, TestCategory>::missing_identity("not a row")) {
+            Err(error) => error,
+            Ok(_) => panic!("found an identity morphism on a word outside 2^E"),
+        };
+        assert!(matches!(error, CalfErrors::MissingIdentityMorphism(..)));
+        assert!(std::error::Error::source(&error).is_some());
+    }
+
+    #[tokio::test]
+    async fn freshly_added_words_are_mapped_by_their_identity_morphisms() {
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(|word| word.ends_with('a'))).await;
+        calf.add_prefix("ba").await.unwrap();
+        calf.create_suffix_power_set().await.unwrap();
+
+        let prefix = calf.prefix.clone();
+        for word in prefix.get_all_objects().await.unwrap() {
+            assert!(prefix.get_identity_morphism(&**word).await.is_ok(), "no identity morphism on {}", word.category_id());
+        }
+        calf.add_power_set_morphism(&prefix).await.unwrap();
+        assert!(!calf.category.get_hom_set(&*prefix, &*calf.suffix_power_set).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn prefix_alphabet_to_hypothesis_is_built_once() {
+        // every word has the same row, so the table is closed from the start
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(|_| true)).await;
+        let morphism = calf.build_or_get_prefix_alphabet_to_hypothesis().await.unwrap();
+
+        assert_eq!(morphism.source_object().category_id(), calf.prefix_alphabet.category_id());
+        assert!(morphism == calf.build_or_get_prefix_alphabet_to_hypothesis().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn prefix_alphabet_to_hypothesis_names_the_row_missing_from_h() {
+        // a has the row true while the only row of S is the one of the empty word, false
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(|word| word.ends_with('a'))).await;
+
+        let result = calf.build_or_get_prefix_alphabet_to_hypothesis().await;
+        assert!(matches!(result, Err(CalfErrors::HypothesisNotClosed(word)) if word == "a"));
+    }
+
+    #[tokio::test]
+    async fn power_set_holds_only_the_rows_of_the_table() {
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(|word| word.len() % 3 == 0)).await;
+        // 21 suffixes, far more than all 2^21 rows could be added for
+        calf.add_suffix(&"a".repeat(20)).await.unwrap();
+
+        // S is the empty word, whose row differs from the shared row of a and b
+        assert_eq!(calf.suffix_power_set.get_all_objects().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn power_set_of_no_suffixes_holds_the_empty_row() {
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(|word| word.ends_with('a'))).await;
+        calf.suffix = Arc::new(<TestCategory as CategoryTrait>::Object::new().await.unwrap());
+        calf.create_suffix_power_set().await.unwrap();
+
+        let rows: Vec<String> = calf.suffix_power_set.get_all_objects().await.unwrap().into_iter()
+            .map(|row| row.category_id().to_string())
+            .collect();
+        assert_eq!(rows, vec![""]);
+    }
+
+    #[tokio::test]
+    async fn power_set_of_one_suffix_holds_the_rows_of_s_and_s_a() {
+        // E is the empty word alone, a ends in a while the empty word and b do not
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(|word| word.ends_with('a'))).await;
+        assert_eq!(calf.suffix_columns().await.unwrap(), vec![""]);
+        calf.create_suffix_power_set().await.unwrap();
+
+        let rows: HashSet<String> = calf.suffix_power_set.get_all_objects().await.unwrap().into_iter()
+            .map(|row| row.category_id().to_string())
+            .collect();
+        assert_eq!(rows, HashSet::from(["true".to_string(), "false".to_string()]));
+
+        // over b alone no word ends in a, so every row is false
+        let mut calf = learner(vec!["b"], PredicateOracle::new(|word| word.ends_with('a'))).await;
+        calf.create_suffix_power_set().await.unwrap();
+        assert_eq!(calf.suffix_power_set.get_all_objects().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn restore_brings_back_the_cache_and_the_counters() {
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(|word| word.ends_with('a'))).await;
+        let snapshot = calf.snapshot();
+        let (stats, cache_stats) = (calf.stats(), calf.cache_stats());
+
+        calf.add_suffix("ab").await.unwrap();
+        assert_ne!(calf.stats().membership_queries, stats.membership_queries);
+        calf.restore(snapshot);
+
+        assert_eq!(calf.stats().membership_queries, stats.membership_queries);
+        assert_eq!(calf.cache_stats(), cache_stats);
+        assert_eq!(calf.membership_cache.len(), stats.membership_queries);
+    }
+
+    #[tokio::test]
+    async fn restored_snapshots_let_strategies_be_compared_independently() {
+        // the second symbol from the end is an a. the first table rejects everything, so ab is a counterexample
+        let predicate = |word: &str| word.chars().rev().nth(1) == Some('a');
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(predicate)).await;
+        let snapshot = calf.snapshot();
+
+        calf.set_counterexample_strategy(CounterexampleStrategy::AllPrefixes);
+        calf.process_counterexample("ab").await.unwrap();
+        let all_prefixes_rows = calf.observation_rows(&calf.prefix).await.unwrap();
+        assert_eq!(calf.suffix_columns().await.unwrap(), vec![""]);
+
+        calf.restore(snapshot);
+        calf.set_counterexample_strategy(CounterexampleStrategy::RivestSchapire);
+        calf.process_counterexample("ab").await.unwrap();
+        let rivest_schapire_rows = calf.observation_rows(&calf.prefix).await.unwrap();
+
+        // nothing AllPrefixes added to S is left, and the outcome is the one of a fresh learner
+        assert_eq!(all_prefixes_rows.keys().collect::<HashSet<_>>(), HashSet::from([&"".to_string(), &"a".to_string(), &"ab".to_string()]));
+        assert_eq!(rivest_schapire_rows.keys().collect::<Vec<_>>(), vec![""]);
+        assert_eq!(calf.suffix_columns().await.unwrap(), vec!["", "b"]);
+
+        let mut fresh = learner(vec!["a", "b"], PredicateOracle::new(predicate)).await;
+        fresh.set_counterexample_strategy(CounterexampleStrategy::RivestSchapire);
+        fresh.process_counterexample("ab").await.unwrap();
+        assert_eq!(fresh.observation_rows(&fresh.prefix).await.unwrap(), rivest_schapire_rows);
+        assert_eq!(fresh.stats(), calf.stats());
+    }
+
+    #[tokio::test]
+    async fn exhausted_rounds_return_the_current_hypothesis() {
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(|word| word.ends_with('a'))).await;
+
+        match calf.run_with_budget(0, usize::MAX).await.unwrap() {
+            RunOutcome::RoundsExhausted(hypothesis) => assert!(hypothesis.accepts(&symbols(&["b", "a"]))),
+            _ => panic!("expected the rounds to run out"),
+        }
+        assert_eq!(calf.stats().equivalence_queries, 0);
+    }
+
+    #[tokio::test]
+    async fn converges_only_once_the_oracle_accepts_the_hypothesis() {
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(|word| word.ends_with("ab"))).await;
+        assert!(!calf.is_converged().await.unwrap());
+
+        calf.run_with_budget(1, usize::MAX).await.unwrap();
+        assert!(!calf.is_converged().await.unwrap());
+
+        calf.run().await.unwrap();
+        assert!(calf.is_converged().await.unwrap());
+
+        // a new suffix changes the table, whose hypothesis was not checked yet
+        calf.add_suffix("abba").await.unwrap();
+        assert!(!calf.is_converged().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn query_budget_is_checked_within_a_round() {
+        // the first table is not closed, so the budget runs out while closing it
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(|word| word.ends_with('a'))).await;
+
+        let outcome = calf.run_with_budget(usize::MAX, 1).await.unwrap();
+        assert!(matches!(outcome, RunOutcome::QueryBudgetExhausted(_)));
+        assert_eq!(calf.stats().equivalence_queries, 0);
+    }
+
+    #[tokio::test]
+    async fn timeouts_leave_a_hypothesis_to_extract() {
+        // the first table is closed and consistent, rejecting every word, and wrong about aa
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(|word| word.contains("aa"))).await;
+
+        assert!(matches!(calf.run_with_timeout(Duration::ZERO).await, Err(CalfErrors::Timeout)));
+        assert_eq!(calf.stats().equivalence_queries, 0);
+        let dfa = calf.extract_automaton().await.unwrap();
+        assert_eq!(dfa.state_count(), Some(1));
+        assert!(!dfa.accepts(&symbols(&["a", "a"])));
+    }
+
+    #[tokio::test]
+    async fn dot_exports_hold_the_learned_automaton() {
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(|word| word.ends_with('a'))).await;
+        calf.run().await.unwrap();
+        let dfa = calf.extract_automaton().await.unwrap();
+
+        let path = std::env::temp_dir().join(format!("calf_dot_{}.dot", std::process::id()));
+        let path = path.to_str().unwrap();
+        calf.export_dot(path).await.unwrap();
+        let dot = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(dot, dfa.to_dot());
+        let accepting = dfa.accepting().iter().next().unwrap();
+        let double_circled: Vec<&str> = dot.lines().filter(|line| line.contains("doublecircle")).collect();
+        assert_eq!(double_circled, vec![format!("    \"{}\" [shape=doublecircle];", accepting)]);
+        assert_eq!(dot.matches("[label=\"a\"]").count(), 2);
+        assert_eq!(dot.matches("[label=\"b\"]").count(), 2);
+    }
+
+    #[tokio::test]
+    async fn traced_dot_exports_follow_words_in_the_oracle_format() {
+        let mut calf = builder(vec!["a", "b"], PredicateOracle::new(|word| word.ends_with('a'))).await
+            .symbol_separator(" ")
+            .build().await
+            .unwrap();
+        calf.run().await.unwrap();
+        let dfa = calf.extract_automaton().await.unwrap();
+
+        let path = std::env::temp_dir().join(format!("calf_trace_{}.dot", std::process::id()));
+        let path = path.to_str().unwrap();
+        calf.export_dot_with_trace("b a", path).await.unwrap();
+        let dot = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(dot, dfa.to_dot_with_trace(&symbols(&["b", "a"])));
+        assert!(dot.contains("label=\"b a is accepted\""));
+        assert!(matches!(calf.export_dot_with_trace("b c", path).await, Err(CalfErrors::InvalidWord(_))));
+    }
+
+    #[tokio::test]
+    async fn sessions_round_trip_without_asking_the_oracle_again() {
+        let predicate = |word: &str| word.ends_with('a');
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(predicate)).await;
+        calf.set_counterexample_strategy(CounterexampleStrategy::AllPrefixes);
+        calf.set_max_counterexample_length(7);
+        calf.set_export_path("calf_session.json");
+        calf.set_verbose(true);
+        calf.add_prefix("ba").await.unwrap();
+        calf.add_suffix("ab").await.unwrap();
+
+        let path = std::env::temp_dir().join(format!("calf_session_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        calf.save_session(path).await.unwrap();
+        let loaded = CALF::<PredicateOracle, TestCategory>::load_session(path, PredicateOracle::new(predicate)).await.unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded.stats().membership_queries, 0);
+        assert_eq!(loaded.counterexample_strategy, CounterexampleStrategy::AllPrefixes);
+        assert_eq!(loaded.max_counterexample_length, Some(7));
+        assert_eq!(loaded.export_path.as_deref(), Some("calf_session.json"));
+        assert!(loaded.verbose);
+        assert_eq!(loaded.membership_cache, calf.membership_cache);
+        assert_eq!(loaded.observation_rows(&loaded.prefix).await.unwrap(), calf.observation_rows(&calf.prefix).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn resumed_sessions_learn_what_an_uninterrupted_run_learns() {
+        let predicate = |word: &str| word.chars().rev().nth(1) == Some('a');
+        let mut uninterrupted = learner(vec!["a", "b"], PredicateOracle::new(predicate)).await;
+        uninterrupted.run().await.unwrap();
+
+        let mut interrupted = learner(vec!["a", "b"], PredicateOracle::new(predicate)).await;
+        assert!(matches!(interrupted.run_with_budget(1, usize::MAX).await.unwrap(), RunOutcome::RoundsExhausted(_)));
+        let path = std::env::temp_dir().join(format!("calf_session_resumed_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        interrupted.save_session(path).await.unwrap();
+        let mut resumed = CALF::<PredicateOracle, TestCategory>::load_session(path, PredicateOracle::new(predicate)).await.unwrap();
+        std::fs::remove_file(path).unwrap();
+        resumed.run().await.unwrap();
+
+        let (expected, learned) = (uninterrupted.extract_automaton().await.unwrap(), resumed.extract_automaton().await.unwrap());
+        assert_eq!(learned.state_count(), Some(4));
+        assert_eq!(learned.to_dot(), expected.to_dot());
+    }
+
+    #[tokio::test]
+    async fn sessions_with_words_outside_the_alphabet_are_rejected() {
+        let calf = learner(vec!["a", "b"], PredicateOracle::new(|_| true)).await;
+        let path = std::env::temp_dir().join(format!("calf_session_invalid_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        calf.save_session(path).await.unwrap();
+        let contents = std::fs::read_to_string(path).unwrap().replace("\"prefixes\": [\n    \"\"", "\"prefixes\": [\n    \"\",\n    \"c\"");
+        std::fs::write(path, contents).unwrap();
+
+        let result = CALF::<PredicateOracle, TestCategory>::load_session(path, PredicateOracle::new(|_| true)).await;
+        std::fs::remove_file(path).unwrap();
+        assert!(matches!(result, Err(CalfErrors::SessionError(_))));
+    }
+
+    #[tokio::test]
+    async fn sessions_with_duplicate_symbols_are_rejected() {
+        let calf = learner(vec!["a", "b"], PredicateOracle::new(|_| true)).await;
+        let path = std::env::temp_dir().join(format!("calf_session_duplicate_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        calf.save_session(path).await.unwrap();
+        let mut session: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        session["alphabet"] = serde_json::json!(["a", "b", "a"]);
+        std::fs::write(path, session.to_string()).unwrap();
+
+        let result = CALF::<PredicateOracle, TestCategory>::load_session(path, PredicateOracle::new(|_| true)).await;
+        std::fs::remove_file(path).unwrap();
+        assert!(matches!(result, Err(CalfErrors::InvalidAlphabet(details)) if details.contains("duplicate symbol a")));
+    }
+
+    #[tokio::test]
+    async fn inconsistent_rows_are_told_apart_by_a_symbol_and_a_suffix() {
+        // a at the second to last position: "" and a share the row false, but a and aa do not
+        let predicate = |word: &str| word.len() >= 2 && word.as_bytes()[word.len() - 2] == b'a';
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(predicate)).await;
+        calf.add_prefix("a").await.unwrap();
+        calf.add_prefix("aa").await.unwrap();
+
+        assert!(matches!(calf.is_closed().await.unwrap(), Closed::Closed));
+        assert!(matches!(calf.is_consistent().await.unwrap(), Consistent::NotConsistent(suffixes) if suffixes == vec!["a"]));
+    }
+
+    #[tokio::test]
+    async fn even_numbers_of_symbols_need_a_consistency_round() {
+        // the prefixes of the counterexamples end up with equal rows that only a longer suffix separates
+        let predicate = |word: &str| word.matches('a').count() % 2 == 0 && word.matches('b').count() % 2 == 0;
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(predicate)).await;
+        calf.set_counterexample_strategy(CounterexampleStrategy::AllPrefixes);
+        calf.run().await.unwrap();
+
+        let automaton = calf.extract_automaton().await.unwrap();
+        assert!(calf.stats().consistency_rounds > 0);
+        assert_eq!(automaton.state_count(), Some(4));
+        for word in [vec!["a", "a"], vec!["a", "b", "b", "a"], vec![]] {
+            assert!(automaton.accepts(&symbols(&word)));
+        }
+        for word in [vec!["a"], vec!["a", "b"], vec!["b", "b", "b"]] {
+            assert!(!automaton.accepts(&symbols(&word)));
+        }
+    }
+
+    #[tokio::test]
+    async fn explained_states_differ_on_a_suffix_of_e() {
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(|word| word.ends_with('a'))).await;
+        calf.run().await.unwrap();
+        let queries = calf.stats().membership_queries;
+
+        assert_eq!(calf.explain_states("", "a").await.unwrap(), Some((String::new(), false, true)));
+        assert_eq!(calf.explain_states("a", "a").await.unwrap(), None);
+        assert!(matches!(calf.explain_states("", "c").await, Err(CalfErrors::UnknownState(_))));
+        assert_eq!(calf.stats().membership_queries, queries);
+    }
+
+    // a writer whose contents stay readable after it is handed to CALF
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(bytes)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn runs_write_nothing_unless_verbose() {
+        for verbose in [false, true] {
+            let log = SharedBuffer::default();
+            let mut calf = learner(vec!["a", "b"], PredicateOracle::new(|word| word.ends_with('a'))).await;
+            calf.set_log_writer(log.clone());
+            calf.set_verbose(verbose);
+            calf.run().await.unwrap();
+
+            assert_eq!(log.0.lock().unwrap().is_empty(), !verbose);
+        }
+    }
+
+    #[tokio::test]
+    async fn duplicate_symbols_are_rejected() {
+        let result = builder(vec!["a", "b", "a"], PredicateOracle::new(|_| true)).await.build().await;
+        assert!(matches!(result, Err(CalfErrors::InvalidAlphabet(details)) if details.contains("duplicate symbol a")));
+    }
+
+    #[tokio::test]
+    async fn observation_table_json_holds_the_cells_of_the_table() {
+        let predicate = |word: &str| word.ends_with("ab");
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(predicate)).await;
+        calf.run().await.unwrap();
+
+        let path = std::env::temp_dir().join(format!("calf_table_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        calf.export_observation_table_json(path).await.unwrap();
+        let table: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let suffixes = calf.suffix_columns().await.unwrap();
+        let mut prefixes: Vec<String> = calf.observation_rows(&calf.prefix).await.unwrap().into_keys().collect();
+        prefixes.sort();
+        assert_eq!(table["version"], OBSERVATION_TABLE_SCHEMA_VERSION);
+        assert_eq!(table["suffixes"], serde_json::json!(suffixes));
+        assert_eq!(table["upper_rows"], serde_json::json!(prefixes));
+        let words = table["upper_rows"].as_array().unwrap().iter().chain(table["lower_rows"].as_array().unwrap());
+        for word in words.map(|word| word.as_str().unwrap()) {
+            let expected: Vec<bool> = suffixes.iter().map(|suffix| predicate(&format!("{}{}", word, suffix))).collect();
+            assert_eq!(table["cells"][word], serde_json::json!(expected), "{}", word);
+        }
+    }
+
+    #[tokio::test]
+    async fn spurious_morphisms_from_fs_to_h_are_an_error_every_time() {
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(|_| true)).await;
+        let morphism = calf.build_or_get_prefix_alphabet_to_hypothesis().await.unwrap();
+        let mappings: HashMap<_, _> = morphism.arrow_mappings().into_iter().flatten()
+            .map(|(source, target)| (source.clone(), target.clone()))
+            .collect();
+        let spurious = Morphism::new_with_mappings(morphism.source_object().clone(), morphism.target_object().clone(), mappings);
+        calf.category.add_morphism(Arc::new(spurious)).await.unwrap();
+
+        for _ in 0..3 {
+            let result = calf.build_or_get_prefix_alphabet_to_hypothesis().await;
+            assert!(matches!(result, Err(CalfErrors::MultipleMorphismsFromFSToH)));
+        }
+    }
+
+    #[test]
+    fn single_morphism_needs_exactly_one() {
+        assert!(matches!(single_morphism(vec![1], CalfErrors::UnknownError, CalfErrors::EmptyAlphabet), Ok(1)));
+        assert!(matches!(single_morphism(Vec::<u8>::new(), CalfErrors::UnknownError, CalfErrors::EmptyAlphabet), Err(CalfErrors::UnknownError)));
+        assert!(matches!(single_morphism(vec![1, 2], CalfErrors::UnknownError, CalfErrors::EmptyAlphabet), Err(CalfErrors::EmptyAlphabet)));
+    }
+
+    #[tokio::test]
+    async fn partition_has_a_group_per_learned_state() {
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(|word| word.ends_with("ab"))).await;
+        calf.run().await.unwrap();
+
+        let partition = calf.export_partition().await.unwrap();
+        let automaton = calf.extract_automaton().await.unwrap();
+        assert_eq!(Some(partition.len()), automaton.state_count());
+        assert!(partition.iter().any(|group| group.contains(&String::new())));
+    }
+
+    // even number of a's and a number of b's divisible by 3, whose counterexamples are long
+    fn even_a_and_b_by_three(word: &str) -> bool {
+        word.matches('a').count() % 2 == 0 && word.matches('b').count() % 3 == 0
+    }
+
+    // the rows and the columns of the table once the language is learned with the strategy
+    async fn learned_table_size(strategy: CounterexampleStrategy) -> (usize, usize, LearningStats) {
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(even_a_and_b_by_three)).await;
+        calf.set_counterexample_strategy(strategy);
+        calf.run().await.unwrap();
+        let rows = calf.observation_rows(&calf.prefix).await.unwrap().len();
+        (rows, calf.suffix_columns().await.unwrap().len(), calf.stats())
+    }
+
+    #[tokio::test]
+    async fn rivest_schapire_adds_one_suffix_per_counterexample() {
+        let (rows, columns, stats) = learned_table_size(CounterexampleStrategy::RivestSchapire).await;
+        let (naive_rows, _, _) = learned_table_size(CounterexampleStrategy::AllPrefixes).await;
+
+        // the empty word, then at most one suffix per counterexample and per consistency round
+        assert!(columns <= stats.equivalence_queries + stats.consistency_rounds);
+        assert!(rows < naive_rows);
+    }
+
+    #[tokio::test]
+    async fn counterexample_effects_name_the_new_states() {
+        for (strategy, added_prefixes, added_suffixes, new_state) in [
+            (CounterexampleStrategy::RivestSchapire, vec![], vec!["a"], "a"),
+            (CounterexampleStrategy::AllPrefixes, vec!["a", "aa"], vec![], "aa"),
+        ] {
+            let mut calf = learner(vec!["a", "b"], PredicateOracle::new(|word| word.contains("aa"))).await;
+            calf.set_counterexample_strategy(strategy);
+            // every row is the one of the empty word, the hypothesis rejects everything
+            calf.run_with_budget(0, usize::MAX).await.unwrap();
+            assert_eq!(calf.last_counterexample_effect(), None);
+
+            calf.process_counterexample("aa").await.unwrap();
+            let effect = calf.last_counterexample_effect().unwrap();
+            assert_eq!(effect.counterexample, "aa");
+            assert_eq!(effect.added_prefixes, added_prefixes);
+            assert_eq!(effect.added_suffixes, added_suffixes);
+            assert_eq!(effect.new_states, vec![new_state.to_string()], "{strategy:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn counterexample_analysis_returns_a_single_suffix() {
+        let mut calf = learner(vec!["a"], PredicateOracle::new(|word| word.len() % 6 == 0)).await;
+        // closes the table without asking for a counterexample, the hypothesis only accepts the empty word
+        calf.run_with_budget(0, usize::MAX).await.unwrap();
+        let columns = calf.suffix_columns().await.unwrap();
+
+        let suffix = calf.analyze_counterexample("aaaaaa").await.unwrap();
+        let words: Vec<String> = suffix.get_all_objects().await.unwrap().into_iter()
+            .map(|word| word.category_id().to_string())
+            .collect();
+        assert_eq!(words.len(), 1);
+        assert!("aaaaaa".ends_with(&words[0]));
+        assert_eq!(calf.suffix_columns().await.unwrap(), columns);
+    }
+
+    #[tokio::test]
+    async fn rivest_schapire_tables_are_smaller_than_all_prefixes_tables() {
+        let (rows, columns, _) = learned_table_size(CounterexampleStrategy::RivestSchapire).await;
+        let (naive_rows, naive_columns, _) = learned_table_size(CounterexampleStrategy::AllPrefixes).await;
+
+        assert!(columns <= naive_columns);
+        assert!(rows * columns < naive_rows * naive_columns);
+    }
+
+    #[tokio::test]
+    async fn duplicate_transition_functions_are_reported() {
+        let mut calf = learner(vec!["a", "b"], PredicateOracle::new(|word| word.ends_with('a'))).await;
+        calf.run().await.unwrap();
+        let transition = calf.get_or_add_hypothesis_transition().await.unwrap();
+        assert!(transition == calf.get_or_add_hypothesis_transition().await.unwrap());
+
+        let mappings: HashMap<_, _> = transition.arrow_mappings().into_iter().flatten()
+            .map(|(source, target)| (source.clone(), target.clone()))
+            .collect();
+        let duplicate = Morphism::new_with_mappings(transition.source_object().clone(), transition.target_object().clone(), mappings);
+        calf.category.add_morphism(Arc::new(duplicate)).await.unwrap();
+
+        let result = calf.get_or_add_hypothesis_transition().await;
+        assert!(matches!(result, Err(CalfErrors::MultipleMorphismsFromFHtoH)));
+    }
+}
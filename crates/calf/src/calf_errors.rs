@@ -1,4 +1,6 @@
+use std::fmt;
 use category_theory::core::errors::Errors;
+use crate::oracle_trait::OracleFailure;
 
 #[derive(Debug)]
 pub enum CalfErrors {
@@ -10,17 +12,64 @@ pub enum CalfErrors {
     MembershipQueryObjectNotFound,
     MultipleMorphismsFromFSToH,
     NoMorphismFromFStoFH,
+    NoMorphismFromFHtoPowerset,
+    NoMorphismFromFStoH,
     InvalidMappingFromFStoFH,
+    InvalidMappingFromFStoH,
     InvalidMappingFromFHtoPowerset,
     InvalidMappingFromHtoPowerset,
     MultipleMorphismsFromFStoH,
     MultipleMorphismsFromFHtoH,
     ErrorAddingPowersetMorphism,
+    FactorizationFailed(String, Errors),
+    InvalidWord(String),
+    UnknownState(String),
+    NoMorphismToPowerSet,
+    MultipleMorphismsToPowerSet,
+    MissingIdentityMorphism(String, Errors),
+    InvalidAlphabet(String),
+    InvalidCounterexample(String),
+    HypothesisNotClosed(String),
+    HypothesisNotConsistent(String),
     CategoryExportError(String),
+    Category(Errors),
+    ExportError(String),
+    Timeout,
+    ProductConstructionFailed(Errors),
+    EmptyAlphabet,
+    MissingEmptyWord(String),
+    SessionError(String),
+    EquivalenceQueryFailed(OracleFailure),
 }
 
 impl From<Errors> for CalfErrors {
-    fn from(_: Errors) -> Self {
-        CalfErrors::UnknownError
+    fn from(error: Errors) -> Self {
+        CalfErrors::Category(error)
+    }
+}
+
+impl fmt::Display for CalfErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalfErrors::Category(error) => write!(f, "category error: {:?}", error),
+            CalfErrors::EquivalenceQueryFailed(error) => write!(f, "equivalence query failed: {}", error),
+            CalfErrors::FactorizationFailed(morphism, error) => write!(f, "failed to factorize {}: {:?}", morphism, error),
+            CalfErrors::MissingIdentityMorphism(object, error) => write!(f, "no identity morphism on {}: {:?}", object, error),
+            CalfErrors::ProductConstructionFailed(error) => write!(f, "failed to build S x A: {:?}", error),
+            error => write!(f, "{:?}", error),
+        }
+    }
+}
+
+impl std::error::Error for CalfErrors {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CalfErrors::Category(error)
+            | CalfErrors::FactorizationFailed(_, error)
+            | CalfErrors::MissingIdentityMorphism(_, error)
+            | CalfErrors::ProductConstructionFailed(error) => Some(error),
+            CalfErrors::EquivalenceQueryFailed(error) => Some(error.as_ref()),
+            _ => None,
+        }
     }
 }
\ No newline at end of file
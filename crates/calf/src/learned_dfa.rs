@@ -0,0 +1,647 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use crate::oracle_trait::{AutomatonTrait, OracleTrait};
+
+/// DFA read off a closed and consistent observation table.
+///
+/// States are named by their row over the suffixes, words are sequences of alphabet symbols.
+/// A DFA may be partial: a word reaching a missing transition is rejected, as if the transition
+/// led to a rejecting sink. `with_total_completion` adds that sink explicitly.
+#[derive(Clone, Debug)]
+pub struct LearnedDfa {
+    states: HashSet<String>,
+    alphabet: Vec<String>,
+    transitions: HashMap<(String, String), String>,
+    initial: String,
+    accepting: HashSet<String>,
+    // put between symbols when a word is spelled out for the oracle
+    symbol_separator: String,
+}
+
+
+/// A way in which a `LearnedDfa` is not a well formed DFA, see `LearnedDfa::validate`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationError {
+    UnknownInitialState(String),
+    UnknownAcceptingState(String),
+    // (source, symbol, target)
+    UnknownTransitionState(String, String, String),
+    UnknownTransitionSymbol(String, String),
+    // (state, symbol)
+    MissingTransition(String, String),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::UnknownInitialState(state) => write!(f, "initial state {} is not a state", state),
+            ValidationError::UnknownAcceptingState(state) => write!(f, "accepting state {} is not a state", state),
+            ValidationError::UnknownTransitionState(source, symbol, target) => write!(f, "transition {} --{}--> {} uses an unknown state", source, symbol, target),
+            ValidationError::UnknownTransitionSymbol(source, symbol) => write!(f, "transition from {} on {} uses an unknown symbol", source, symbol),
+            ValidationError::MissingTransition(state, symbol) => write!(f, "no transition from {} on {}", state, symbol),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// A language that is seldom the one meant, see `LearnedDfa::trivial_language`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrivialLanguage {
+    Empty,
+    Universal,
+}
+
+
+impl LearnedDfa {
+    pub fn new(
+        states: HashSet<String>,
+        alphabet: Vec<String>,
+        transitions: HashMap<(String, String), String>,
+        initial: String,
+        accepting: HashSet<String>,
+    ) -> Self {
+        LearnedDfa { states, alphabet, transitions, initial, accepting, symbol_separator: String::new() }
+    }
+
+    /// Sets the separator put between symbols when a word is spelled out for the oracle.
+    pub fn with_symbol_separator(mut self, symbol_separator: &str) -> Self {
+        self.symbol_separator = symbol_separator.to_string();
+        self
+    }
+
+    pub fn states(&self) -> &HashSet<String> {
+        &self.states
+    }
+
+    pub fn alphabet(&self) -> &[String] {
+        &self.alphabet
+    }
+
+    pub fn transitions(&self) -> &HashMap<(String, String), String> {
+        &self.transitions
+    }
+
+    pub fn initial(&self) -> &String {
+        &self.initial
+    }
+
+    pub fn accepting(&self) -> &HashSet<String> {
+        &self.accepting
+    }
+
+    /// Renders the DFA in Graphviz DOT, accepting states are double circled.
+    pub fn to_dot(&self) -> String {
+        self.render_dot(None)
+    }
+
+    /// Renders the DFA in Graphviz DOT with the states and transitions `word` goes through in red,
+    /// and a graph label saying whether it is accepted.
+    pub fn to_dot_with_trace(&self, word: &[String]) -> String {
+        self.render_dot(Some(word))
+    }
+
+    /// Returns the states `word` goes through from the initial state, stopping at a missing transition.
+    pub fn trace(&self, word: &[String]) -> Vec<&String> {
+        let mut states = vec![&self.initial];
+        for symbol in word {
+            match self.transitions.get(&(states[states.len() - 1].clone(), symbol.clone())) {
+                Some(target) => states.push(target),
+                None => break,
+            }
+        }
+        states
+    }
+
+    fn render_dot(&self, word: Option<&[String]>) -> String {
+        let mut states: Vec<&String> = self.states.iter().collect();
+        states.sort();
+        let mut transitions: Vec<(&(String, String), &String)> = self.transitions.iter().collect();
+        transitions.sort();
+        let trace = word.map(|word| self.trace(word)).unwrap_or_default();
+        let traced_edges: HashSet<(&String, &String)> = trace.iter().copied().zip(word.into_iter().flatten()).collect();
+        let highlight = |traced: bool| if traced { ", color=red, penwidth=2" } else { "" };
+
+        let mut dot = String::from("digraph hypothesis {\n    rankdir=LR;\n    __start [shape=point];\n");
+        if let Some(word) = word {
+            let verdict = if self.accepts(word) { "accepted" } else { "rejected" };
+            dot += &format!("    label=\"{} is {}\";\n", escape(&word.join(&self.symbol_separator)), verdict);
+        }
+        for state in states {
+            let shape = if self.accepting.contains(state) { "doublecircle" } else { "circle" };
+            dot += &format!("    \"{}\" [shape={}{}];\n", escape(state), shape, highlight(trace.contains(&state)));
+        }
+        dot += &format!("    __start -> \"{}\";\n", escape(&self.initial));
+        for ((source, symbol), target) in transitions {
+            dot += &format!("    \"{}\" -> \"{}\" [label=\"{}\"{}];\n",
+                escape(source), escape(target), escape(symbol), highlight(traced_edges.contains(&(source, symbol))));
+        }
+        dot += "}\n";
+        dot
+    }
+
+    /// Checks that the initial and accepting states are states and that every transition goes
+    /// between states on a symbol of the alphabet, returning every violation found.
+    ///
+    /// Determinism needs no check since transitions are keyed by state and symbol. Missing
+    /// transitions are allowed, `validate_complete` also reports them.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = vec![];
+        if !self.states.contains(&self.initial) {
+            errors.push(ValidationError::UnknownInitialState(self.initial.clone()));
+        }
+        let mut accepting: Vec<&String> = self.accepting.difference(&self.states).collect();
+        accepting.sort();
+        errors.extend(accepting.into_iter().map(|state| ValidationError::UnknownAcceptingState(state.clone())));
+
+        let mut transitions: Vec<(&(String, String), &String)> = self.transitions.iter().collect();
+        transitions.sort();
+        for ((source, symbol), target) in transitions {
+            if !self.states.contains(source) || !self.states.contains(target) {
+                errors.push(ValidationError::UnknownTransitionState(source.clone(), symbol.clone(), target.clone()));
+            }
+            if !self.alphabet.contains(symbol) {
+                errors.push(ValidationError::UnknownTransitionSymbol(source.clone(), symbol.clone()));
+            }
+        }
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    /// Same as `validate`, also reporting every state and symbol without a transition.
+    pub fn validate_complete(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = self.validate().err().unwrap_or_default();
+        let mut states: Vec<&String> = self.states.iter().collect();
+        states.sort();
+        for state in states {
+            for symbol in &self.alphabet {
+                if !self.transitions.contains_key(&(state.clone(), symbol.clone())) {
+                    errors.push(ValidationError::MissingTransition(state.clone(), symbol.clone()));
+                }
+            }
+        }
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    /// Returns the DFA with a rejecting sink state receiving every missing transition, named
+    /// `sink` with as many `'` as needed to be a new state. A complete DFA is returned as is.
+    pub fn with_total_completion(mut self) -> LearnedDfa {
+        let missing: Vec<(String, String)> = self.states.iter()
+            .flat_map(|state| self.alphabet.iter().map(move |symbol| (state.clone(), symbol.clone())))
+            .filter(|key| !self.transitions.contains_key(key))
+            .collect();
+        if missing.is_empty() {
+            return self;
+        }
+        let mut sink = "sink".to_string();
+        while self.states.contains(&sink) {
+            sink.push('\'');
+        }
+        for key in missing {
+            self.transitions.insert(key, sink.clone());
+        }
+        for symbol in &self.alphabet {
+            self.transitions.insert((sink.clone(), symbol.clone()), sink.clone());
+        }
+        self.states.insert(sink);
+        self
+    }
+
+    /// Returns the state reached on `word`, or `None` if some transition is missing.
+    pub fn run(&self, word: &[String]) -> Option<&String> {
+        word.iter().try_fold(&self.initial, |state, symbol| {
+            self.transitions.get(&(state.clone(), symbol.clone()))
+        })
+    }
+
+    /// Returns every word of at most `bound` symbols on which the DFA and `oracle` disagree, as
+    /// (word, accepted by the DFA, accepted by the oracle), shortest first and lexicographic
+    /// within a length. Unlike an equivalence query this shows all the errors up to the bound.
+    pub fn misclassified_words(&self, oracle: &impl OracleTrait<String>, bound: usize) -> Vec<(String, bool, bool)> {
+        let mut alphabet = self.alphabet.clone();
+        alphabet.sort();
+        let mut misclassified = vec![];
+        let mut words: Vec<Vec<String>> = vec![vec![]];
+        for length in 0..=bound {
+            for word in &words {
+                let spelled = word.join(&self.symbol_separator);
+                let (hypothesis, target) = (self.accepts(word), oracle.membership_query(&spelled));
+                if hypothesis != target {
+                    misclassified.push((spelled, hypothesis, target));
+                }
+            }
+            if length == bound || alphabet.is_empty() {
+                break;
+            }
+            words = words.iter()
+                .flat_map(|word| alphabet.iter().map(move |symbol| [word.as_slice(), std::slice::from_ref(symbol)].concat()))
+                .collect();
+        }
+        misclassified
+    }
+
+    /// Returns whether the DFA accepts no word or every word. Either is more often a sign of an
+    /// oracle answering every membership query the same than the language meant.
+    pub fn trivial_language(&self) -> Option<TrivialLanguage> {
+        let reachable = self.reachable_states();
+        if !reachable.iter().any(|state| self.accepting.contains(*state)) {
+            return Some(TrivialLanguage::Empty);
+        }
+        let universal = reachable.iter().all(|state| {
+            self.accepting.contains(*state)
+                && self.alphabet.iter().all(|symbol| self.transitions.contains_key(&((*state).clone(), symbol.clone())))
+        });
+        universal.then_some(TrivialLanguage::Universal)
+    }
+
+    fn reachable_states(&self) -> HashSet<&String> {
+        let mut reachable = HashSet::from([&self.initial]);
+        let mut queue = vec![&self.initial];
+        while let Some(state) = queue.pop() {
+            for symbol in &self.alphabet {
+                if let Some(target) = self.transitions.get(&(state.clone(), symbol.clone()))
+                    && reachable.insert(target) {
+                    queue.push(target);
+                }
+            }
+        }
+        reachable
+    }
+
+    /// Returns the minimal DFA of the same language, keeping the reachable states and merging
+    /// the equivalent ones. Each merged state is named by the smallest of the names it replaces.
+    pub fn minimize(&self) -> LearnedDfa {
+        let mut alphabet = self.alphabet.clone();
+        alphabet.sort();
+
+        let mut states: Vec<&String> = self.reachable_states().into_iter().collect();
+        states.sort();
+
+        // Moore's refinement: states start split by acceptance and are split by the classes of
+        // their targets until no class splits any more
+        let mut classes: HashMap<&String, usize> = states.iter()
+            .map(|state| (*state, usize::from(self.accepting.contains(*state))))
+            .collect();
+        let mut class_count = classes.values().collect::<HashSet<_>>().len();
+        loop {
+            let mut ids: HashMap<(usize, Vec<Option<usize>>), usize> = HashMap::new();
+            let refined: HashMap<&String, usize> = states.iter()
+                .map(|state| {
+                    let targets = alphabet.iter()
+                        .map(|symbol| self.transitions.get(&((*state).clone(), symbol.clone())).map(|target| classes[target]))
+                        .collect();
+                    let next_id = ids.len();
+                    (*state, *ids.entry((classes[*state], targets)).or_insert(next_id))
+                })
+                .collect();
+            classes = refined;
+            if ids.len() == class_count {
+                break;
+            }
+            class_count = ids.len();
+        }
+
+        // states are sorted, so the first one met in a class is its smallest name
+        let mut names: HashMap<usize, &String> = HashMap::new();
+        for state in &states {
+            names.entry(classes[*state]).or_insert(*state);
+        }
+        let name = |state: &String| names[&classes[state]].clone();
+
+        let transitions = self.transitions.iter()
+            .filter(|((source, _), _)| classes.contains_key(source))
+            .map(|((source, symbol), target)| ((name(source), symbol.clone()), name(target)))
+            .collect();
+        LearnedDfa {
+            states: states.iter().map(|state| name(state)).collect(),
+            alphabet: self.alphabet.clone(),
+            transitions,
+            initial: name(&self.initial),
+            accepting: states.iter().filter(|state| self.accepting.contains(**state)).map(|state| name(state)).collect(),
+            symbol_separator: self.symbol_separator.clone(),
+        }
+    }
+
+    /// Returns an anchored regular expression for the language, found by eliminating the states one by one.
+    ///
+    /// Words are matched with their symbols concatenated, the separator is not part of the expression.
+    pub fn to_regex(&self) -> String {
+        let mut states: Vec<&String> = self.states.iter().collect();
+        states.sort();
+        let index: HashMap<&String, usize> = states.iter().enumerate().map(|(i, state)| (*state, i)).collect();
+        // two more nodes, one entering the initial state and one every accepting state leads to
+        let (start, end) = (states.len(), states.len() + 1);
+
+        // an edge is missing when no word leads along it, "" is the empty word
+        let mut edges: HashMap<(usize, usize), String> = HashMap::new();
+        let add_edge = |edges: &mut HashMap<(usize, usize), String>, edge: (usize, usize), expression: String| {
+            let expression = match edges.remove(&edge) {
+                Some(existing) => regex_union(&existing, &expression),
+                None => expression,
+            };
+            edges.insert(edge, expression);
+        };
+        add_edge(&mut edges, (start, index[&self.initial]), String::new());
+        for state in &self.accepting {
+            add_edge(&mut edges, (index[state], end), String::new());
+        }
+        let mut transitions: Vec<(&(String, String), &String)> = self.transitions.iter().collect();
+        transitions.sort();
+        for ((source, symbol), target) in transitions {
+            add_edge(&mut edges, (index[source], index[target]), escape_regex(symbol));
+        }
+
+        for state in 0..states.len() {
+            let repeated = edges.remove(&(state, state)).map(|expression| regex_star(&expression)).unwrap_or_default();
+            let mut incoming = vec![];
+            let mut outgoing = vec![];
+            for ((source, target), expression) in edges.iter() {
+                if *target == state {
+                    incoming.push((*source, expression.clone()));
+                } else if *source == state {
+                    outgoing.push((*target, expression.clone()));
+                }
+            }
+            edges.retain(|(source, target), _| *source != state && *target != state);
+            incoming.sort();
+            outgoing.sort();
+            for (source, into) in &incoming {
+                for (target, out_of) in &outgoing {
+                    let expression = regex_concat(&regex_concat(into, &repeated), out_of);
+                    add_edge(&mut edges, (*source, *target), expression);
+                }
+            }
+        }
+
+        match edges.get(&(start, end)) {
+            Some(expression) => format!("^{}$", expression),
+            // no word is accepted, and an empty class matches no character
+            None => "^[a&&b]$".to_string(),
+        }
+    }
+}
+
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_regex(symbol: &str) -> String {
+    symbol.chars()
+        .map(|character| match character {
+            '\\' | '.' | '+' | '*' | '?' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$' | '#' | '&' | '-' | '~' => format!("\\{}", character),
+            character => character.to_string(),
+        })
+        .collect()
+}
+
+// operators on the expressions of to_regex. "" is the empty word, every result is either a
+// plain concatenation or grouped, so no operand needs parentheses for precedence
+
+fn regex_union(left: &str, right: &str) -> String {
+    match (left, right) {
+        _ if left == right => left.to_string(),
+        ("", other) | (other, "") => format!("(?:{})?", other),
+        _ => format!("(?:{}|{})", left, right),
+    }
+}
+
+fn regex_concat(left: &str, right: &str) -> String {
+    format!("{}{}", left, right)
+}
+
+fn regex_star(expression: &str) -> String {
+    match expression {
+        "" => String::new(),
+        _ => format!("(?:{})*", expression),
+    }
+}
+
+
+impl AutomatonTrait<String> for LearnedDfa {
+    fn accepts(&self, word: &[String]) -> bool {
+        self.run(word).is_some_and(|state| self.accepting.contains(state))
+    }
+
+    fn state_count(&self) -> Option<usize> {
+        Some(self.states.len())
+    }
+
+    fn to_dot(&self) -> Option<String> {
+        Some(LearnedDfa::to_dot(self))
+    }
+
+    fn symbols(&self) -> Option<Vec<String>> {
+        Some(self.alphabet.clone())
+    }
+
+    fn symbol_separator(&self) -> Option<String> {
+        Some(self.symbol_separator.clone())
+    }
+
+    fn initial_state(&self) -> Option<String> {
+        Some(self.initial.clone())
+    }
+
+    fn transition(&self, state: &str, symbol: &String) -> Option<String> {
+        self.transitions.get(&(state.to_string(), symbol.clone())).cloned()
+    }
+
+    fn is_accepting_state(&self, state: &str) -> bool {
+        self.accepting.contains(state)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dfa(states: &[&str], transitions: &[(&str, &str, &str)], initial: &str, accepting: &[&str]) -> LearnedDfa {
+        let owned = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<HashSet<_>>();
+        let mut alphabet: Vec<String> = transitions.iter().map(|(_, symbol, _)| symbol.to_string()).collect();
+        alphabet.sort();
+        alphabet.dedup();
+        let transitions = transitions.iter()
+            .map(|(source, symbol, target)| ((source.to_string(), symbol.to_string()), target.to_string()))
+            .collect();
+        LearnedDfa::new(owned(states), alphabet, transitions, initial.to_string(), owned(accepting))
+    }
+
+    fn word(symbols: &str) -> Vec<String> {
+        symbols.chars().map(|symbol| symbol.to_string()).collect()
+    }
+
+    #[test]
+    fn minimizing_merges_equivalent_states_and_drops_unreachable_ones() {
+        // even number of a's, with the even state split in two and an unreachable state
+        let even_a = dfa(
+            &["e1", "e2", "o", "u"],
+            &[("e1", "a", "o"), ("o", "a", "e2"), ("e2", "a", "o"), ("u", "a", "e1")],
+            "e1",
+            &["e1", "e2"]);
+        let minimal = even_a.minimize();
+
+        assert_eq!(minimal.state_count(), Some(2));
+        assert_eq!(minimal.initial(), "e1");
+        assert_eq!(minimal.accepting(), &HashSet::from(["e1".to_string()]));
+        for length in 0..6 {
+            assert_eq!(minimal.accepts(&word(&"a".repeat(length))), even_a.accepts(&word(&"a".repeat(length))));
+        }
+    }
+
+    #[test]
+    fn regexes_come_from_eliminating_the_states() {
+        let any = dfa(&["q"], &[("q", "a", "q"), ("q", "b", "q")], "q", &["q"]);
+        assert_eq!(any.to_regex(), "^(?:(?:a|b))*$");
+
+        let dot_then_b = dfa(&["p", "q", "r"], &[("p", ".", "q"), ("q", "b", "r")], "p", &["r"]);
+        assert_eq!(dot_then_b.to_regex(), "^\\.b$");
+
+        let nothing = dfa(&["q"], &[("q", "a", "q")], "q", &[]);
+        assert_eq!(nothing.to_regex(), "^[a&&b]$");
+    }
+
+    #[test]
+    fn dot_double_circles_accepting_states_and_escapes_labels() {
+        let quoted = dfa(&["p", "say \"q\""], &[("p", "\\", "say \"q\""), ("say \"q\"", "\"", "p")], "p", &["say \"q\""]);
+        let dot = quoted.to_dot();
+
+        assert_eq!(dot, concat!(
+            "digraph hypothesis {\n",
+            "    rankdir=LR;\n",
+            "    __start [shape=point];\n",
+            "    \"p\" [shape=circle];\n",
+            "    \"say \\\"q\\\"\" [shape=doublecircle];\n",
+            "    __start -> \"p\";\n",
+            "    \"p\" -> \"say \\\"q\\\"\" [label=\"\\\\\"];\n",
+            "    \"say \\\"q\\\"\" -> \"p\" [label=\"\\\"\"];\n",
+            "}\n",
+        ));
+    }
+
+    #[test]
+    fn validation_reports_every_violation() {
+        let even_a = dfa(&["e", "o"], &[("e", "a", "o"), ("o", "a", "e")], "e", &["e"]);
+        assert_eq!(even_a.validate_complete(), Ok(()));
+
+        let partial = dfa(&["p", "q"], &[("p", "a", "q"), ("q", "b", "q")], "p", &["q"]);
+        assert_eq!(partial.validate(), Ok(()));
+        assert_eq!(partial.validate_complete(), Err(vec![
+            ValidationError::MissingTransition("p".to_string(), "b".to_string()),
+            ValidationError::MissingTransition("q".to_string(), "a".to_string()),
+        ]));
+
+        let broken = LearnedDfa::new(
+            HashSet::from(["p".to_string()]),
+            vec!["a".to_string()],
+            HashMap::from([
+                (("p".to_string(), "a".to_string()), "x".to_string()),
+                (("p".to_string(), "b".to_string()), "p".to_string()),
+            ]),
+            "i".to_string(),
+            HashSet::from(["p".to_string(), "y".to_string()]));
+        assert_eq!(broken.validate(), Err(vec![
+            ValidationError::UnknownInitialState("i".to_string()),
+            ValidationError::UnknownAcceptingState("y".to_string()),
+            ValidationError::UnknownTransitionState("p".to_string(), "a".to_string(), "x".to_string()),
+            ValidationError::UnknownTransitionSymbol("p".to_string(), "b".to_string()),
+        ]));
+    }
+
+    #[test]
+    fn missing_transitions_reject_unless_completed() {
+        // a then b, with no transition on b first or on a after a
+        let partial = dfa(&["p", "q", "r"], &[("p", "a", "q"), ("q", "b", "r")], "p", &["r"]);
+        assert!(partial.accepts(&word("ab")));
+        assert!(!partial.accepts(&word("b")));
+        assert!(!partial.accepts(&word("aab")));
+        assert_eq!(partial.run(&word("b")), None);
+
+        let total = partial.clone().with_total_completion();
+        assert_eq!(total.validate_complete(), Ok(()));
+        assert_eq!(total.state_count(), Some(4));
+        assert_eq!(total.run(&word("ba")), Some(&"sink".to_string()));
+        for symbols in ["", "a", "b", "ab", "aab", "abb", "ba"] {
+            assert_eq!(total.accepts(&word(symbols)), partial.accepts(&word(symbols)), "{symbols}");
+        }
+
+        let named_sink = dfa(&["sink", "q"], &[("sink", "a", "q")], "sink", &["q"]).with_total_completion();
+        assert!(named_sink.states().contains("sink'"));
+        let complete = dfa(&["q"], &[("q", "a", "q")], "q", &["q"]).with_total_completion();
+        assert_eq!(complete.state_count(), Some(1));
+    }
+
+    #[test]
+    fn empty_and_universal_languages_are_flagged() {
+        // the accepting state is unreachable
+        let empty = dfa(&["p", "q"], &[("p", "a", "p"), ("q", "a", "q")], "p", &["q"]);
+        assert_eq!(empty.trivial_language(), Some(TrivialLanguage::Empty));
+        let universal = dfa(&["p", "q"], &[("p", "a", "p"), ("q", "a", "p")], "p", &["p"]);
+        assert_eq!(universal.trivial_language(), Some(TrivialLanguage::Universal));
+        // a missing transition rejects
+        let partial = dfa(&["p", "q"], &[("p", "a", "p"), ("q", "b", "q")], "p", &["p", "q"]);
+        assert_eq!(partial.trivial_language(), None);
+        let even = dfa(&["p", "q"], &[("p", "a", "q"), ("q", "a", "p")], "p", &["p"]);
+        assert_eq!(even.trivial_language(), None);
+    }
+
+    struct EndsWithB;
+
+    impl OracleTrait<String> for EndsWithB {
+        fn membership_query(&self, input: &String) -> bool {
+            input.ends_with('b')
+        }
+
+        fn equivalence_query<H: AutomatonTrait<String>>(&self, hypothesis: &H) -> Option<String> {
+            self.find_counterexample_by_enumeration(hypothesis, &[])
+        }
+    }
+
+    #[test]
+    fn misclassified_words_list_every_disagreement_up_to_the_bound() {
+        // accepts the words of odd length
+        let odd = dfa(&["p", "q"], &[("p", "a", "q"), ("p", "b", "q"), ("q", "a", "p"), ("q", "b", "p")], "p", &["q"]);
+        let misclassified = odd.misclassified_words(&EndsWithB, 2);
+        assert_eq!(misclassified, vec![
+            ("a".to_string(), true, false),
+            ("ab".to_string(), false, true),
+            ("bb".to_string(), false, true),
+        ]);
+        assert_eq!(odd.misclassified_words(&EndsWithB, 0), vec![]);
+        for (word, hypothesis, target) in odd.misclassified_words(&EndsWithB, 4) {
+            assert_ne!(hypothesis, target, "{word}");
+            assert!(word.len() <= 4);
+        }
+
+        // accepts the words ending in a, spelled with spaces
+        let ends_with_a = dfa(&["p", "q"], &[("p", "a", "q"), ("p", "b", "p"), ("q", "a", "q"), ("q", "b", "p")], "p", &["q"])
+            .with_symbol_separator(" ");
+        let misclassified = ends_with_a.misclassified_words(&EndsWithB, 2);
+        assert_eq!(misclassified.len(), 6);
+        assert_eq!(misclassified[2], ("a a".to_string(), true, false));
+        assert_eq!(misclassified[3], ("a b".to_string(), false, true));
+    }
+
+    #[test]
+    fn traced_dot_highlights_the_transitions_taken() {
+        // counts a's modulo 3, b loops
+        let mod_three = dfa(
+            &["0", "1", "2"],
+            &[("0", "a", "1"), ("1", "a", "2"), ("2", "a", "0"), ("0", "b", "0"), ("1", "b", "1"), ("2", "b", "2")],
+            "0",
+            &["0"]);
+        assert_eq!(mod_three.trace(&word("aba")), vec!["0", "1", "1", "2"]);
+
+        let dot = mod_three.to_dot_with_trace(&word("aba"));
+        let highlighted: Vec<&str> = dot.lines().filter(|line| line.contains("->") && line.contains("color=red")).collect();
+        assert_eq!(highlighted, vec![
+            "    \"0\" -> \"1\" [label=\"a\", color=red, penwidth=2];",
+            "    \"1\" -> \"2\" [label=\"a\", color=red, penwidth=2];",
+            "    \"1\" -> \"1\" [label=\"b\", color=red, penwidth=2];",
+        ]);
+        assert!(dot.contains("    \"0\" [shape=doublecircle, color=red, penwidth=2];"));
+        assert!(dot.contains("    label=\"aba is rejected\";"));
+        assert!(!dot.contains("\"2\" -> \"0\" [label=\"a\", color"));
+        assert!(mod_three.to_dot_with_trace(&word("aaa")).contains("label=\"aaa is accepted\""));
+    }
+}
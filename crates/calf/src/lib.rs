@@ -1,3 +1,9 @@
 pub mod calf;
 pub mod oracle_trait;
 pub mod calf_errors;
+pub mod learned_dfa;
+pub mod caching_oracle;
+pub mod query_counter;
+
+#[cfg(test)]
+mod test_support;
@@ -1,28 +1,300 @@
+use std::error::Error;
+use std::future::ready;
+
 pub trait QueryInputTrait: Clone {
     type Symbol: Clone + Eq;
 
     /// Concatenate / append a symbol (string case) or graft (tree case)
     fn append_symbol(&self, symbol: &Self::Symbol) -> Self;
+
+    /// Concatenate two inputs
+    fn concat(&self, other: &Self) -> Self;
 }
 
 /// Oracle for membership and equivalence queries
-pub trait OracleTrait<W: QueryInputTrait + ?Sized> {
+pub trait OracleTrait<W: QueryInputTrait> {
     fn membership_query(&self, input: &W) -> bool;
 
     fn equivalence_query<H: AutomatonTrait<W>>(
         &self,
         hypothesis: &H,
     ) -> Option<W>;
+
+    /// Equivalence query for oracles that may fail to decide, e.g. when a search runs out of room.
+    ///
+    /// `CALF` asks this one, so an oracle that can fail overrides it instead of answering `None`,
+    /// which would accept the hypothesis.
+    fn try_equivalence_query<H: AutomatonTrait<W>>(
+        &self,
+        hypothesis: &H,
+    ) -> Result<Option<W>, OracleFailure> {
+        Ok(self.equivalence_query(hypothesis))
+    }
+
+    /// Maximum length of the words tried by `find_counterexample_by_enumeration`, if the oracle has one.
+    fn search_bound(&self) -> Option<usize> {
+        None
+    }
+
+    /// Tries all words of up to `search_bound` symbols in shortlex order, returning the first one
+    /// on which membership and the hypothesis disagree. Without a bound of the oracle the search
+    /// goes `DEFAULT_SEARCH_MARGIN` symbols past the number of states of the hypothesis, or up to
+    /// `DEFAULT_SEARCH_BOUND` symbols if the hypothesis does not report its size.
+    ///
+    /// The symbols are the hypothesis' own when it reports them, so that a symbol spelled with
+    /// several characters is tried as a whole, and the single symbols of `alphabet` otherwise.
+    /// Words are spelled with the hypothesis' symbol separator between symbols.
+    /// A brute force equivalence query for oracles that can only answer membership: words are
+    /// generated one at a time, but a search over k symbols still asks up to k^bound queries,
+    /// and a distinguishing word longer than the bound is missed.
+    fn find_counterexample_by_enumeration<H: AutomatonTrait<W>>(
+        &self,
+        hypothesis: &H,
+        alphabet: &[W::Symbol],
+    ) -> Option<W>
+    where
+        W: Default + Ord,
+    {
+        let max_length = self.search_bound().unwrap_or_else(|| {
+            hypothesis.state_count().map_or(DEFAULT_SEARCH_BOUND, |states| states + DEFAULT_SEARCH_MARGIN)
+        });
+        let mut tokens = hypothesis.symbols().unwrap_or_else(|| {
+            alphabet.iter().map(|symbol| W::default().append_symbol(symbol)).collect()
+        });
+        tokens.sort();
+        tokens.dedup();
+        let separator = hypothesis.symbol_separator().unwrap_or_default();
+        for length in 0..=max_length {
+            if length > 0 && tokens.is_empty() {
+                break;
+            }
+            // the symbols of the current word as indices into tokens, counted up like an odometer
+            // so the words of a length come in lexicographic order
+            let mut indices = vec![0; length];
+            loop {
+                let symbols: Vec<W> = indices.iter().map(|&index| tokens[index].clone()).collect();
+                let word = symbols.iter().enumerate().fold(W::default(), |word, (position, symbol)| match position {
+                    0 => symbol.clone(),
+                    _ => word.concat(&separator).concat(symbol),
+                });
+                if self.membership_query(&word) != hypothesis.accepts(&symbols) {
+                    return Some(word);
+                }
+                match indices.iter().rposition(|&index| index + 1 < tokens.len()) {
+                    Some(position) => {
+                        indices[position] += 1;
+                        indices[position + 1..].fill(0);
+                    },
+                    None => break,
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Why an oracle could not answer a query.
+pub type OracleFailure = Box<dyn Error + Send + Sync>;
+
+/// Search bound of `OracleTrait::find_counterexample_by_enumeration` when neither the oracle nor
+/// the size of the hypothesis gives one.
+pub const DEFAULT_SEARCH_BOUND: usize = 8;
+
+/// Symbols `OracleTrait::find_counterexample_by_enumeration` goes past the number of states of the
+/// hypothesis, for oracles without a bound of their own.
+pub const DEFAULT_SEARCH_MARGIN: usize = 2;
+
+/// Oracle whose queries may have to wait, e.g. on a database or an HTTP service.
+///
+/// `CALF` drives its oracle through this trait. Every `OracleTrait` implements it through the
+/// blanket impl below, answering right away, so synchronous oracles such as `RegexOracle` keep
+/// working unchanged. An oracle doing I/O should implement this trait instead of `OracleTrait`,
+/// with `async fn`s whose futures are `Send`, so a learning run can be spawned on a multithreaded
+/// runtime.
+pub trait AsyncOracleTrait<W: QueryInputTrait> {
+    fn membership_query(&self, input: &W) -> impl Future<Output = bool> + Send;
+
+    fn equivalence_query<H: AutomatonTrait<W> + Sync>(
+        &self,
+        hypothesis: &H,
+    ) -> impl Future<Output = Result<Option<W>, OracleFailure>> + Send;
+}
+
+impl<W: QueryInputTrait + Send, O: OracleTrait<W>> AsyncOracleTrait<W> for O {
+    fn membership_query(&self, input: &W) -> impl Future<Output = bool> + Send {
+        ready(OracleTrait::membership_query(self, input))
+    }
+
+    fn equivalence_query<H: AutomatonTrait<W> + Sync>(
+        &self,
+        hypothesis: &H,
+    ) -> impl Future<Output = Result<Option<W>, OracleFailure>> + Send {
+        ready(OracleTrait::try_equivalence_query(self, hypothesis))
+    }
 }
 
 pub trait AutomatonTrait<I> {
     fn accepts(&self, word: &[I]) -> bool;
+
+    /// Number of states, if known. Lets oracles bound the search for a counterexample.
+    fn state_count(&self) -> Option<usize> {
+        None
+    }
+
+    /// Graphviz DOT rendering, if the automaton can produce one. Lets oracles show the hypothesis.
+    fn to_dot(&self) -> Option<String> {
+        None
+    }
+
+    /// Symbols the automaton reads, if known. Lets oracles build words out of whole symbols.
+    fn symbols(&self) -> Option<Vec<I>> {
+        None
+    }
+
+    /// Separator the oracle expects between symbols, if any.
+    fn symbol_separator(&self) -> Option<I> {
+        None
+    }
+
+    /// Name of the initial state, if the automaton exposes its states. Together with `transition`
+    /// and `is_accepting_state` this lets oracles explore the hypothesis instead of enumerating words.
+    fn initial_state(&self) -> Option<String> {
+        None
+    }
+
+    /// State reached from `state` on `symbol`, `None` if there is no such transition.
+    fn transition(&self, _state: &str, _symbol: &I) -> Option<String> {
+        None
+    }
+
+    fn is_accepting_state(&self, _state: &str) -> bool {
+        false
+    }
 }
 
 impl QueryInputTrait for String {
-    type Symbol = ();
+    type Symbol = char;
 
     fn append_symbol(&self, symbol: &Self::Symbol) -> Self {
-        todo!()
+        let mut word = self.clone();
+        word.push(*symbol);
+        word
+    }
+
+    fn concat(&self, other: &Self) -> Self {
+        format!("{self}{other}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // accepts the words of exactly one symbol
+    struct Short;
+
+    impl AutomatonTrait<String> for Short {
+        fn accepts(&self, word: &[String]) -> bool {
+            (1..=1).contains(&word.len())
+        }
+    }
+
+    // accepts nothing
+    struct Empty;
+
+    impl OracleTrait<String> for Empty {
+        fn membership_query(&self, _input: &String) -> bool {
+            false
+        }
+
+        fn equivalence_query<H: AutomatonTrait<String>>(&self, hypothesis: &H) -> Option<String> {
+            self.find_counterexample_by_enumeration(hypothesis, &['b', 'a', 'b'])
+        }
+
+        fn search_bound(&self) -> Option<usize> {
+            Some(3)
+        }
+    }
+
+    // accepts nothing, reporting the given number of states
+    struct Sized(usize);
+
+    impl AutomatonTrait<String> for Sized {
+        fn accepts(&self, _word: &[String]) -> bool {
+            false
+        }
+
+        fn state_count(&self) -> Option<usize> {
+            Some(self.0)
+        }
     }
-}
\ No newline at end of file
+
+    // accepts the words of at least four symbols, without a bound of its own
+    struct Long;
+
+    impl OracleTrait<String> for Long {
+        fn membership_query(&self, input: &String) -> bool {
+            input.chars().count() >= 4
+        }
+
+        fn equivalence_query<H: AutomatonTrait<String>>(&self, hypothesis: &H) -> Option<String> {
+            self.find_counterexample_by_enumeration(hypothesis, &['b', 'a'])
+        }
+    }
+
+    #[test]
+    fn default_bound_follows_the_size_of_the_hypothesis() {
+        assert_eq!(OracleTrait::equivalence_query(&Long, &Sized(1)), None);
+        assert_eq!(OracleTrait::equivalence_query(&Long, &Sized(2)), Some("aaaa".to_string()));
+    }
+
+    #[test]
+    fn enumeration_goes_through_every_word_of_a_length_in_order() {
+        // the only counterexample is the last word of length 3 in lexicographic order
+        struct Last;
+
+        impl OracleTrait<String> for Last {
+            fn membership_query(&self, input: &String) -> bool {
+                input == "ccc"
+            }
+
+            fn equivalence_query<H: AutomatonTrait<String>>(&self, hypothesis: &H) -> Option<String> {
+                self.find_counterexample_by_enumeration(hypothesis, &['c', 'a', 'b'])
+            }
+        }
+
+        assert_eq!(OracleTrait::equivalence_query(&Last, &Sized(1)), Some("ccc".to_string()));
+        assert_eq!(OracleTrait::equivalence_query(&Last, &Sized(0)), None);
+    }
+
+    #[test]
+    fn enumeration_returns_the_shortlex_least_counterexample_for_an_unsorted_alphabet() {
+        assert_eq!(OracleTrait::equivalence_query(&Empty, &Short), Some("a".to_string()));
+    }
+
+    fn assert_send<T: Send>(value: T) -> T {
+        value
+    }
+
+    #[test]
+    fn adapted_oracles_answer_with_send_futures() {
+        let word = String::new();
+        let membership = assert_send(AsyncOracleTrait::membership_query(&Empty, &word));
+        let equivalence = assert_send(AsyncOracleTrait::equivalence_query(&Empty, &Short));
+        drop((membership, equivalence));
+    }
+
+    #[test]
+    fn appending_to_the_empty_string_gives_the_symbol() {
+        assert_eq!(String::new().append_symbol(&'a'), "a");
+        assert_eq!(String::new().concat(&"ab".to_string()), "ab");
+    }
+
+    #[test]
+    fn appending_multi_byte_characters_keeps_whole_characters() {
+        let word = "é".to_string().append_symbol(&'λ').append_symbol(&'🦀');
+        assert_eq!(word, "éλ🦀");
+        assert_eq!(word.chars().collect::<Vec<_>>(), vec!['é', 'λ', '🦀']);
+        assert_eq!(word.len(), 2 + 2 + 4);
+    }
+}
@@ -0,0 +1,78 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::oracle_trait::{AutomatonTrait, OracleFailure, OracleTrait, QueryInputTrait};
+
+/// Handle on the counts of a [`QueryCounter`], still readable once the counter has been moved into `CALF`.
+#[derive(Clone, Debug, Default)]
+pub struct QueryCounts {
+    membership: Arc<AtomicUsize>,
+    equivalence: Arc<AtomicUsize>,
+}
+
+
+impl QueryCounts {
+    pub fn membership_count(&self) -> usize {
+        self.membership.load(Ordering::Relaxed)
+    }
+
+    pub fn equivalence_count(&self) -> usize {
+        self.equivalence.load(Ordering::Relaxed)
+    }
+}
+
+
+/// Oracle wrapper counting the membership and equivalence queries forwarded to the inner oracle.
+pub struct QueryCounter<W: QueryInputTrait, O: OracleTrait<W>> {
+    inner: O,
+    counts: QueryCounts,
+    _word: PhantomData<fn(&W)>,
+}
+
+
+impl<W: QueryInputTrait, O: OracleTrait<W>> QueryCounter<W, O> {
+    /// Returns the counter together with a handle on its counts.
+    pub fn new(inner: O) -> (Self, QueryCounts) {
+        let counts = QueryCounts::default();
+        let counter = QueryCounter {
+            inner,
+            counts: counts.clone(),
+            _word: PhantomData,
+        };
+        (counter, counts)
+    }
+
+    pub fn membership_count(&self) -> usize {
+        self.counts.membership_count()
+    }
+
+    pub fn equivalence_count(&self) -> usize {
+        self.counts.equivalence_count()
+    }
+
+    pub fn counts(&self) -> QueryCounts {
+        self.counts.clone()
+    }
+
+    pub fn into_inner(self) -> O {
+        self.inner
+    }
+}
+
+
+impl<W: QueryInputTrait, O: OracleTrait<W>> OracleTrait<W> for QueryCounter<W, O> {
+    fn membership_query(&self, input: &W) -> bool {
+        self.counts.membership.fetch_add(1, Ordering::Relaxed);
+        self.inner.membership_query(input)
+    }
+
+    fn equivalence_query<H: AutomatonTrait<W>>(&self, hypothesis: &H) -> Option<W> {
+        self.counts.equivalence.fetch_add(1, Ordering::Relaxed);
+        self.inner.equivalence_query(hypothesis)
+    }
+
+    fn try_equivalence_query<H: AutomatonTrait<W>>(&self, hypothesis: &H) -> Result<Option<W>, OracleFailure> {
+        self.counts.equivalence.fetch_add(1, Ordering::Relaxed);
+        self.inner.try_equivalence_query(hypothesis)
+    }
+}
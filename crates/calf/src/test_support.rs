@@ -0,0 +1,63 @@
+//! Oracles and helpers shared by the unit tests of this crate.
+
+use std::sync::{Arc, Mutex};
+use category_theory::core::base_category::BaseCategory;
+use category_theory::core::dynamic_category::DynamicCategory;
+use category_theory::core::persistable_category::PersistableCategory;
+use crate::calf::{CALF, CalfBuilder};
+use crate::oracle_trait::{AutomatonTrait, OracleTrait};
+
+pub(crate) type TestCategory = BaseCategory<PersistableCategory<DynamicCategory>>;
+
+/// Oracle for the words satisfying a predicate, recording every membership query.
+///
+/// Equivalence enumerates words of up to 8 symbols of the hypothesis.
+pub(crate) struct PredicateOracle {
+    predicate: Box<dyn Fn(&str) -> bool + Send + Sync>,
+    queries: Arc<Mutex<Vec<String>>>,
+}
+
+
+impl PredicateOracle {
+    pub(crate) fn new(predicate: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        PredicateOracle { predicate: Box::new(predicate), queries: Arc::new(Mutex::new(vec![])) }
+    }
+
+    /// Membership queries asked so far, in order. Stays readable once the oracle is moved into a learner.
+    pub(crate) fn queries(&self) -> Arc<Mutex<Vec<String>>> {
+        self.queries.clone()
+    }
+}
+
+
+impl OracleTrait<String> for PredicateOracle {
+    fn membership_query(&self, input: &String) -> bool {
+        self.queries.lock().unwrap().push(input.clone());
+        (self.predicate)(input)
+    }
+
+    fn equivalence_query<H: AutomatonTrait<String>>(&self, hypothesis: &H) -> Option<String> {
+        self.find_counterexample_by_enumeration(hypothesis, &[])
+    }
+
+    fn search_bound(&self) -> Option<usize> {
+        Some(8)
+    }
+}
+
+
+/// Creates a learner over `alphabet` backed by a fresh category.
+pub(crate) async fn learner(alphabet: Vec<&str>, oracle: PredicateOracle) -> CALF<PredicateOracle, TestCategory> {
+    builder(alphabet, oracle).build().await.expect("failed to create CALF")
+}
+
+/// Builder over `alphabet`, for tests needing other settings.
+pub(crate) async fn builder(alphabet: Vec<&str>, oracle: PredicateOracle) -> CalfBuilder<PredicateOracle, TestCategory> {
+    // every test asks for the database, only the first call creates it
+    let _ = category_theory::init_db(Some("calf_test")).await;
+    CalfBuilder::new(oracle).alphabet(alphabet)
+}
+
+pub(crate) fn symbols(word: &[&str]) -> Vec<String> {
+    word.iter().map(|symbol| symbol.to_string()).collect()
+}
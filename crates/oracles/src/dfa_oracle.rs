@@ -0,0 +1,383 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use calf::oracle_trait::{AutomatonTrait, OracleFailure, OracleTrait};
+use crate::oracle_error::OracleError;
+
+pub type StateId = usize;
+
+// the invisible node whose edge marks the initial state in DOT files
+const DOT_START: &str = "__start";
+
+/// Oracle for the language of an explicit, complete DFA.
+pub struct DfaOracle {
+    alphabet: Vec<char>,
+    transitions: HashMap<(StateId, char), StateId>,
+    initial: StateId,
+    accepting: HashSet<StateId>,
+}
+
+
+
+impl DfaOracle {
+    /// Creates a new `DfaOracle`, checking that every state has a transition on every symbol
+    /// and that all referenced states exist.
+    pub fn new(
+        states: HashSet<StateId>,
+        alphabet: Vec<char>,
+        transitions: HashMap<(StateId, char), StateId>,
+        initial: StateId,
+        accepting: HashSet<StateId>,
+    ) -> Result<Self, OracleError> {
+        if !states.contains(&initial) {
+            return Err(OracleError::InvalidTransitionTable(format!("unknown initial state {initial}")));
+        }
+        if let Some(state) = accepting.iter().find(|state| !states.contains(state)) {
+            return Err(OracleError::InvalidTransitionTable(format!("unknown accepting state {state}")));
+        }
+        for ((source, symbol), target) in &transitions {
+            if !states.contains(source) || !states.contains(target) {
+                return Err(OracleError::InvalidTransitionTable(
+                    format!("transition {source} --{symbol}--> {target} uses an unknown state")));
+            }
+            if !alphabet.contains(symbol) {
+                return Err(OracleError::InvalidTransitionTable(
+                    format!("transition {source} --{symbol}--> {target} uses an unknown symbol")));
+            }
+        }
+        for state in &states {
+            for symbol in &alphabet {
+                if !transitions.contains_key(&(*state, *symbol)) {
+                    return Err(OracleError::InvalidTransitionTable(
+                        format!("missing transition from {state} on {symbol}")));
+                }
+            }
+        }
+        Ok(DfaOracle { alphabet, transitions, initial, accepting })
+    }
+
+    /// Reads a DFA from a DOT file, see `from_dot`.
+    pub fn from_dot_file(path: &str) -> Result<Self, OracleError> {
+        let dot = std::fs::read_to_string(path)
+            .map_err(|error| OracleError::InvalidDotFile(format!("{path}: {error}")))?;
+        Self::from_dot(&dot)
+    }
+
+    /// Reads a DFA from the subset of DOT written by `LearnedDfa::to_dot`: one statement per line,
+    /// nodes with `shape=doublecircle` are accepting, the edge from `__start` gives the initial
+    /// state, and every other edge has a single character `label` as its symbol. Other attributes
+    /// are ignored. Missing transitions go to a new rejecting state, as they reject in `LearnedDfa`.
+    ///
+    /// Errors name the line they are on.
+    pub fn from_dot(dot: &str) -> Result<Self, OracleError> {
+        let mut ids: HashMap<String, StateId> = HashMap::new();
+        let mut id = |name: String| {
+            let next_id = ids.len();
+            *ids.entry(name).or_insert(next_id)
+        };
+        let mut initial = None;
+        let mut accepting = HashSet::new();
+        let mut transitions = HashMap::new();
+        for (number, line) in dot.lines().enumerate() {
+            let error = |reason: &str| OracleError::InvalidDotFile(format!("line {}: {}: {}", number + 1, reason, line.trim()));
+            let statement = line.trim().trim_end_matches(';').trim_end();
+            if statement.is_empty() || statement == "}" || statement.starts_with("digraph") {
+                continue;
+            }
+            let (source, rest) = parse_dot_id(statement).ok_or_else(|| error("expected a node"))?;
+            let rest = rest.trim_start();
+            if rest.starts_with('=') {
+                // a graph attribute e.g. rankdir=LR
+                continue;
+            }
+            match rest.strip_prefix("->") {
+                Some(rest) => {
+                    let (target, rest) = parse_dot_id(rest.trim_start()).ok_or_else(|| error("expected a target node"))?;
+                    let attributes = parse_dot_attributes(rest).ok_or_else(|| error("malformed attributes"))?;
+                    if source == DOT_START {
+                        if initial.replace(id(target)).is_some() {
+                            return Err(error("second initial state"));
+                        }
+                        continue;
+                    }
+                    let mut label = attributes.get("label").ok_or_else(|| error("edge without a label"))?.chars();
+                    let symbol = match (label.next(), label.next()) {
+                        (Some(symbol), None) => symbol,
+                        _ => return Err(error("label is not a single character")),
+                    };
+                    let (source, target) = (id(source), id(target));
+                    if transitions.insert((source, symbol), target).is_some_and(|existing| existing != target) {
+                        return Err(error("second transition on the same symbol"));
+                    }
+                },
+                None => {
+                    let attributes = parse_dot_attributes(rest).ok_or_else(|| error("malformed attributes"))?;
+                    if source == DOT_START {
+                        continue;
+                    }
+                    let state = id(source);
+                    if attributes.get("shape").is_some_and(|shape| shape == "doublecircle") {
+                        accepting.insert(state);
+                    }
+                },
+            }
+        }
+        let initial = initial.ok_or_else(|| OracleError::InvalidDotFile("no edge from __start".to_string()))?;
+
+        let mut alphabet: Vec<char> = transitions.keys().map(|(_, symbol)| *symbol).collect();
+        alphabet.sort();
+        alphabet.dedup();
+        let mut states: HashSet<StateId> = (0..ids.len()).collect();
+        let missing: Vec<(StateId, char)> = states.iter()
+            .flat_map(|state| alphabet.iter().map(move |symbol| (*state, *symbol)))
+            .filter(|key| !transitions.contains_key(key))
+            .collect();
+        if !missing.is_empty() {
+            let sink = ids.len();
+            states.insert(sink);
+            transitions.extend(missing.into_iter().map(|key| (key, sink)));
+            transitions.extend(alphabet.iter().map(|symbol| ((sink, *symbol), sink)));
+        }
+        DfaOracle::new(states, alphabet, transitions, initial, accepting)
+    }
+
+    /// Runs the input through the DFA, returning `None` if it uses a symbol outside the alphabet.
+    pub fn run(&self, input: &str) -> Option<StateId> {
+        self.run_from(self.initial, input)
+    }
+
+    fn run_from(&self, state: StateId, input: &str) -> Option<StateId> {
+        input.chars().try_fold(state, |state, symbol| self.transitions.get(&(state, symbol)).copied())
+    }
+
+    // explores the product of the DFA and the hypothesis breadth first from the pair of initial
+    // states, symbols in order, so the first pair of states telling them apart is reached by a
+    // shortest word, the least one in shortlex order. the product is finite, so the search is
+    // exhaustive and visits each pair once. the separator of the hypothesis is spelled in and
+    // read as characters of the DFA, see try_equivalence_query for one outside the alphabet.
+    fn product_counterexample<H: AutomatonTrait<String>>(&self, hypothesis: &H, initial: String) -> Option<String> {
+        let mut symbols = hypothesis.symbols()
+            .unwrap_or_else(|| self.alphabet.iter().map(|symbol| symbol.to_string()).collect());
+        symbols.sort();
+        symbols.dedup();
+        let separator = hypothesis.symbol_separator().unwrap_or_default();
+
+        // None is the sink reached on a character outside the alphabet, or on a missing transition
+        // of the hypothesis. the root is not marked as visited since words continuing from it take
+        // no separator.
+        let mut visited: HashSet<(Option<StateId>, Option<String>)> = HashSet::new();
+        let mut queue = VecDeque::from([((Some(self.initial), Some(initial)), String::new())]);
+        while let Some(((state, hypothesis_state), word)) = queue.pop_front() {
+            let accepted = state.is_some_and(|state| self.accepting.contains(&state));
+            let hypothesis_accepted = hypothesis_state.as_deref()
+                .is_some_and(|hypothesis_state| hypothesis.is_accepting_state(hypothesis_state));
+            if accepted != hypothesis_accepted {
+                return Some(word);
+            }
+            for symbol in &symbols {
+                let spelled = match word.is_empty() {
+                    true => symbol.clone(),
+                    false => format!("{separator}{symbol}"),
+                };
+                let next = (
+                    state.and_then(|state| self.run_from(state, &spelled)),
+                    hypothesis_state.as_deref().and_then(|hypothesis_state| hypothesis.transition(hypothesis_state, symbol)),
+                );
+                if visited.insert(next.clone()) {
+                    queue.push_back((next, format!("{word}{spelled}")));
+                }
+            }
+        }
+        None
+    }
+
+    /// Checks if the DFA accepts the input.
+    pub fn matches(&self, input: &str) -> bool {
+        self.run(input).is_some_and(|state| self.accepting.contains(&state))
+    }
+}
+
+
+// a quoted DOT id with \" and \\ escapes, or a bare one, and the text after it
+fn parse_dot_id(text: &str) -> Option<(String, &str)> {
+    match text.strip_prefix('"') {
+        Some(quoted) => {
+            let mut id = String::new();
+            let mut characters = quoted.char_indices();
+            while let Some((i, character)) = characters.next() {
+                match character {
+                    '"' => return Some((id, &quoted[i + 1..])),
+                    '\\' => id.push(characters.next()?.1),
+                    character => id.push(character),
+                }
+            }
+            None
+        },
+        None => {
+            let end = text.find(|character: char| !(character.is_alphanumeric() || character == '_' || character == '.'))
+                .unwrap_or(text.len());
+            (end > 0).then(|| (text[..end].to_string(), &text[end..]))
+        },
+    }
+}
+
+// an optional [key=value, ...] list closing the statement
+fn parse_dot_attributes(text: &str) -> Option<HashMap<String, String>> {
+    let text = text.trim();
+    let mut attributes = HashMap::new();
+    if text.is_empty() {
+        return Some(attributes);
+    }
+    let mut rest = text.strip_prefix('[')?.trim_start();
+    loop {
+        if let Some(after) = rest.strip_prefix(']') {
+            return after.trim().is_empty().then_some(attributes);
+        }
+        let (key, after) = parse_dot_id(rest)?;
+        let (value, after) = parse_dot_id(after.trim_start().strip_prefix('=')?.trim_start())?;
+        attributes.insert(key, value);
+        rest = after.trim_start();
+        rest = rest.strip_prefix(',').unwrap_or(rest).trim_start();
+    }
+}
+
+
+impl OracleTrait<String> for DfaOracle {
+    fn membership_query(&self, input: &String) -> bool {
+        self.matches(input)
+    }
+
+    fn equivalence_query<H: AutomatonTrait<String>>(&self, hypothesis: &H) -> Option<String> {
+        match hypothesis.initial_state() {
+            Some(initial) => self.product_counterexample(hypothesis, initial),
+            // a hypothesis that only answers acceptance can't be explored, words are tried instead
+            None => self.find_counterexample_by_enumeration(hypothesis, &self.alphabet),
+        }
+    }
+
+    fn try_equivalence_query<H: AutomatonTrait<String>>(&self, hypothesis: &H) -> Result<Option<String>, OracleFailure> {
+        // a separator outside the alphabet sends every word of two or more symbols to the sink,
+        // so only words of at most one symbol could ever be in the language
+        let separator = hypothesis.symbol_separator().unwrap_or_default();
+        if let Some(character) = separator.chars().find(|character| !self.alphabet.contains(character)) {
+            return Err(OracleError::EquivalenceQueryFailed(
+                format!("separator character {character:?} is not in the alphabet of the DFA")).into());
+        }
+        Ok(self.equivalence_query(hypothesis))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use calf::learned_dfa::LearnedDfa;
+    use super::*;
+
+    // counts symbols modulo `modulus` over {a, b}, accepting the counts in `accepting`
+    fn counter(modulus: usize, accepting: &[usize]) -> DfaOracle {
+        let transitions = (0..modulus)
+            .flat_map(|state| ['a', 'b'].map(|symbol| ((state, symbol), (state + 1) % modulus)))
+            .collect();
+        DfaOracle::new((0..modulus).collect(), vec!['a', 'b'], transitions, 0, accepting.iter().copied().collect()).unwrap()
+    }
+
+    fn learned_counter(modulus: usize, accepting: &[usize]) -> LearnedDfa {
+        let transitions = (0..modulus)
+            .flat_map(|state| ["a", "b"].map(|symbol| ((state.to_string(), symbol.to_string()), ((state + 1) % modulus).to_string())))
+            .collect();
+        LearnedDfa::new(
+            (0..modulus).map(|state| state.to_string()).collect(),
+            vec!["a".to_string(), "b".to_string()],
+            transitions,
+            "0".to_string(),
+            accepting.iter().map(|state| state.to_string()).collect(),
+        )
+    }
+
+    #[test]
+    fn equivalent_hypothesis_has_no_counterexample() {
+        assert_eq!(OracleTrait::equivalence_query(&counter(3, &[0]), &learned_counter(6, &[0, 3])), None);
+    }
+
+    #[test]
+    fn returns_the_shortlex_least_counterexample() {
+        assert_eq!(OracleTrait::equivalence_query(&counter(3, &[0]), &learned_counter(3, &[0, 2])), Some("aa".to_string()));
+    }
+
+    #[test]
+    fn finds_counterexamples_longer_than_any_enumeration() {
+        let counterexample = OracleTrait::equivalence_query(&counter(40, &[0]), &learned_counter(40, &[0, 39]));
+        assert_eq!(counterexample, Some("a".repeat(39)));
+    }
+
+    #[test]
+    fn missing_hypothesis_transitions_reject() {
+        let hypothesis = LearnedDfa::new(
+            HashSet::from(["q".to_string()]),
+            vec!["a".to_string(), "b".to_string()],
+            HashMap::from([(("q".to_string(), "a".to_string()), "q".to_string())]),
+            "q".to_string(),
+            HashSet::from(["q".to_string()]),
+        );
+        let oracle = DfaOracle::new(
+            HashSet::from([0, 1]),
+            vec!['a', 'b'],
+            HashMap::from([((0, 'a'), 0), ((0, 'b'), 1), ((1, 'a'), 1), ((1, 'b'), 1)]),
+            0,
+            HashSet::from([0]),
+        ).unwrap();
+        assert_eq!(OracleTrait::equivalence_query(&oracle, &hypothesis), None);
+    }
+
+    #[test]
+    fn separators_outside_the_alphabet_are_rejected() {
+        let hypothesis = learned_counter(3, &[0]).with_symbol_separator(" ");
+        assert!(OracleTrait::try_equivalence_query(&counter(3, &[0]), &hypothesis).is_err());
+        assert!(OracleTrait::try_equivalence_query(&counter(3, &[0]), &learned_counter(3, &[0])).is_ok());
+    }
+
+    #[test]
+    fn dot_exports_are_read_back() {
+        let learned = learned_counter(3, &[0, 2]);
+        for dot in [learned.to_dot(), learned.to_dot_with_trace(&["a".to_string(), "b".to_string()])] {
+            let oracle = DfaOracle::from_dot(&dot).unwrap();
+            assert_eq!(OracleTrait::equivalence_query(&oracle, &learned), None);
+        }
+
+        let path = std::env::temp_dir().join(format!("dfa_oracle_{}.dot", std::process::id()));
+        std::fs::write(&path, learned.to_dot()).unwrap();
+        let oracle = DfaOracle::from_dot_file(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(oracle.matches("aa"));
+        assert!(!oracle.matches("a"));
+    }
+
+    #[test]
+    fn dot_with_quotes_and_missing_transitions_is_completed() {
+        let dot = concat!(
+            "digraph hypothesis {\n",
+            "    __start [shape=point];\n",
+            "    \"say \\\"hi\\\"\" [shape=circle];\n",
+            "    done [shape=doublecircle];\n",
+            "    __start -> \"say \\\"hi\\\"\";\n",
+            "    \"say \\\"hi\\\"\" -> done [label=\"\\\"\"];\n",
+            "    done -> done [label=x];\n",
+            "}\n",
+        );
+        let oracle = DfaOracle::from_dot(dot).unwrap();
+        assert!(oracle.matches("\"xx"));
+        assert!(!oracle.matches("x"));
+        assert!(!oracle.matches("\"\""));
+    }
+
+    #[test]
+    fn dot_errors_name_the_line() {
+        let error = |dot: &str| match DfaOracle::from_dot(dot) {
+            Err(OracleError::InvalidDotFile(reason)) => reason,
+            _ => panic!("{dot} was read"),
+        };
+        assert!(error("digraph g {\n  __start -> p;\n  p -> p [label=ab];\n}").starts_with("line 3: label is not a single character"));
+        assert!(error("digraph g {\n  p -> p;\n}").starts_with("line 2: edge without a label"));
+        assert!(error("digraph g {\n  __start -> p;\n  p -> q [label=a];\n  p -> p [label=a];\n}").starts_with("line 4"));
+        assert!(error("digraph g {\n  \"p [shape=circle];\n}").starts_with("line 2: expected a node"));
+        assert_eq!(error("digraph g {\n  p -> p [label=a];\n}"), "no edge from __start");
+    }
+}
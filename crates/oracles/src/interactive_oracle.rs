@@ -0,0 +1,175 @@
+use std::io::{self, BufRead, Write};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use calf::oracle_trait::{AutomatonTrait, OracleFailure, OracleTrait};
+use crate::oracle_error::OracleError;
+
+/// Oracle asking a human teacher, by default on stdin and stdout.
+///
+/// Membership queries expect `y` or `n`, equivalence queries expect `yes` or a counterexample.
+/// Malformed answers are asked again. Once the input is closed no answer is the teacher's:
+/// membership queries through `OracleTrait` answer `false`, which a learner may have cached, so
+/// every later equivalence query fails instead of accepting a hypothesis built on them.
+pub struct InteractiveOracle {
+    input: Mutex<Box<dyn BufRead + Send>>,
+    output: Mutex<Box<dyn Write + Send>>,
+    // set once a read finds the input closed
+    closed: AtomicBool,
+}
+
+
+impl InteractiveOracle {
+    /// Creates a new `InteractiveOracle` on stdin and stdout.
+    pub fn new() -> Self {
+        Self::with_io(io::BufReader::new(io::stdin()), io::stdout())
+    }
+
+    /// Creates a new `InteractiveOracle` reading answers from `input` and writing prompts to `output`.
+    pub fn with_io(input: impl BufRead + Send + 'static, output: impl Write + Send + 'static) -> Self {
+        InteractiveOracle {
+            input: Mutex::new(Box::new(input)),
+            output: Mutex::new(Box::new(output)),
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    // prints the prompt and returns the trimmed answer. I/O errors and a closed input are
+    // reported with `failed`, the error of the query being asked.
+    fn ask(&self, prompt: &str, failed: fn(String) -> OracleError) -> Result<String, OracleError> {
+        let mut output = self.output.lock().map_err(|_| OracleError::UnknownError)?;
+        write!(output, "{prompt}").and_then(|_| output.flush())
+            .map_err(|error| failed(error.to_string()))?;
+        let mut line = String::new();
+        let read = self.input.lock().map_err(|_| OracleError::UnknownError)?
+            .read_line(&mut line)
+            .map_err(|error| failed(error.to_string()))?;
+        if read == 0 {
+            self.closed.store(true, Ordering::Relaxed);
+            return Err(failed("the input is closed".to_string()));
+        }
+        Ok(line.trim().to_string())
+    }
+
+    /// Asks whether `input` is in the language.
+    pub fn try_membership_query(&self, input: &str) -> Result<bool, OracleError> {
+        loop {
+            let answer = self.ask(&format!("Is \"{input}\" in the language? [y/n] "), OracleError::MembershipQueryFailed)?;
+            match answer.as_str() {
+                "y" | "yes" => return Ok(true),
+                "n" | "no" => return Ok(false),
+                _ => continue,
+            }
+        }
+    }
+
+    /// Shows the hypothesis and asks for a counterexample, `Ok(None)` if the teacher accepts it.
+    ///
+    /// Fails once the input has been closed, even by an earlier membership query.
+    pub fn try_equivalence_query<H: AutomatonTrait<String>>(&self, hypothesis: &H) -> Result<Option<String>, OracleError> {
+        if self.closed.load(Ordering::Relaxed) {
+            return Err(OracleError::EquivalenceQueryFailed("the input was closed during the run".to_string()));
+        }
+        let description = hypothesis.to_dot()
+            .unwrap_or_else(|| "(the hypothesis has no DOT rendering)".to_string());
+        let prompt = format!("{description}\nIs this the language? [yes / counterexample] ");
+        loop {
+            match self.ask(&prompt, OracleError::EquivalenceQueryFailed)?.as_str() {
+                "yes" | "y" => return Ok(None),
+                "" => continue,
+                answer => return Ok(Some(answer.to_string())),
+            }
+        }
+    }
+}
+
+
+impl Default for InteractiveOracle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+impl OracleTrait<String> for InteractiveOracle {
+    fn membership_query(&self, input: &String) -> bool {
+        // a failed query answers false, the next equivalence query then fails
+        matches!(self.try_membership_query(input), Ok(true))
+    }
+
+    fn equivalence_query<H: AutomatonTrait<String>>(&self, hypothesis: &H) -> Option<String> {
+        self.try_equivalence_query(hypothesis).ok().flatten()
+    }
+
+    fn try_equivalence_query<H: AutomatonTrait<String>>(&self, hypothesis: &H) -> Result<Option<String>, OracleFailure> {
+        Ok(InteractiveOracle::try_equivalence_query(self, hypothesis)?)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+    use std::io::Cursor;
+    use std::sync::Arc;
+    use calf::learned_dfa::LearnedDfa;
+    use super::*;
+
+    // prompts are written here so the tests can read them back
+    #[derive(Clone, Default)]
+    struct Transcript(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Transcript {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(bytes)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn teacher(answers: &str) -> (InteractiveOracle, Transcript) {
+        let transcript = Transcript::default();
+        let oracle = InteractiveOracle::with_io(Cursor::new(answers.to_string()), transcript.clone());
+        (oracle, transcript)
+    }
+
+    fn hypothesis() -> LearnedDfa {
+        LearnedDfa::new(HashSet::from(["q".to_string()]), vec!["a".to_string()], HashMap::new(), "q".to_string(), HashSet::new())
+    }
+
+    #[test]
+    fn membership_reads_yes_and_no() {
+        let (oracle, transcript) = teacher("y\nno\n");
+        assert!(oracle.try_membership_query("ab").unwrap());
+        assert!(!oracle.try_membership_query("ba").unwrap());
+        assert!(String::from_utf8(transcript.0.lock().unwrap().clone()).unwrap().contains("Is \"ab\" in the language?"));
+    }
+
+    #[test]
+    fn malformed_answers_are_asked_again() {
+        let (oracle, transcript) = teacher("maybe\n\n yes \n");
+        assert!(oracle.try_membership_query("a").unwrap());
+        assert_eq!(String::from_utf8(transcript.0.lock().unwrap().clone()).unwrap().matches("Is \"a\"").count(), 3);
+    }
+
+    #[test]
+    fn equivalence_returns_the_counterexample_or_accepts() {
+        let (oracle, _) = teacher("\nabba\nyes\n");
+        assert_eq!(oracle.try_equivalence_query(&hypothesis()).unwrap(), Some("abba".to_string()));
+        assert_eq!(oracle.try_equivalence_query(&hypothesis()).unwrap(), None);
+    }
+
+    #[test]
+    fn closed_input_fails_the_queries() {
+        let (oracle, _) = teacher("");
+        assert!(matches!(oracle.try_equivalence_query(&hypothesis()), Err(OracleError::EquivalenceQueryFailed(_))));
+
+        let (oracle, _) = teacher("yes\n");
+        assert!(oracle.try_membership_query("a").unwrap());
+        assert!(matches!(oracle.try_membership_query("a"), Err(OracleError::MembershipQueryFailed(_))));
+        assert!(!OracleTrait::membership_query(&oracle, &"a".to_string()));
+        // the answer given on the closed input is not the teacher's, so no hypothesis is accepted
+        assert!(OracleTrait::try_equivalence_query(&oracle, &hypothesis()).is_err());
+    }
+}
@@ -0,0 +1,245 @@
+//! End to end tests learning the language of an oracle of this crate with `CALF`.
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use calf::calf::{CALF, CalfBuilder, CounterexampleStrategy};
+use calf::learned_dfa::LearnedDfa;
+use calf::oracle_trait::{AsyncOracleTrait, AutomatonTrait, OracleTrait};
+use calf::query_counter::QueryCounter;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use category_theory::core::base_category::BaseCategory;
+use category_theory::core::dynamic_category::DynamicCategory;
+use category_theory::core::persistable_category::PersistableCategory;
+use crate::myhill_nerode::myhill_nerode_classes;
+use crate::nfa_oracle::NfaOracle;
+use crate::random_walk_equivalence::RandomWalkEquivalence;
+use crate::regex_fixtures::random_regex;
+use crate::regex_oracle::RegexOracle;
+use crate::reverse_oracle::ReverseOracle;
+use crate::window_oracle::WindowOracle;
+
+type TestCategory = BaseCategory<PersistableCategory<DynamicCategory>>;
+
+fn regex(pattern: &str) -> RegexOracle {
+    RegexOracle::new(pattern.to_string()).expect("invalid test pattern")
+}
+
+async fn oracle_learner<O: AsyncOracleTrait<String>>(oracle: O, alphabet: Vec<&str>) -> CALF<O, TestCategory> {
+    // every test asks for the database, only the first call creates it
+    let _ = category_theory::init_db(Some("calf_test")).await;
+    CalfBuilder::new(oracle)
+        .alphabet(alphabet)
+        .build().await
+        .expect("failed to create CALF")
+}
+
+async fn learn_oracle<O: AsyncOracleTrait<String>>(oracle: O, alphabet: Vec<&str>) -> LearnedDfa {
+    let mut calf = oracle_learner(oracle, alphabet).await;
+    calf.run().await.expect("learning failed");
+    calf.extract_automaton().await.expect("no hypothesis after learning")
+}
+
+async fn learn(pattern: &str, alphabet: Vec<&str>) -> LearnedDfa {
+    learn_oracle(regex(pattern), alphabet).await
+}
+
+fn symbols(word: &[&str]) -> Vec<String> {
+    word.iter().map(|symbol| symbol.to_string()).collect()
+}
+
+// the order the adversarial oracle hands out its counterexamples in
+#[derive(Clone, Copy, Debug)]
+enum CounterexampleOrder {
+    LongestFirst,
+    // longest, shortest, longest, ...
+    Alternating,
+}
+
+// answers equivalence queries with the counterexample a shortlex search would give last
+// rather than first, to stress the counterexample handling of the learner
+struct AdversarialOracle {
+    inner: RegexOracle,
+    order: CounterexampleOrder,
+    max_length: usize,
+    equivalence_queries: AtomicUsize,
+}
+
+impl AdversarialOracle {
+    fn new(pattern: &str, order: CounterexampleOrder) -> Self {
+        AdversarialOracle { inner: regex(pattern), order, max_length: 7, equivalence_queries: AtomicUsize::new(0) }
+    }
+}
+
+impl OracleTrait<String> for AdversarialOracle {
+    fn membership_query(&self, input: &String) -> bool {
+        self.inner.membership_query(input)
+    }
+
+    fn equivalence_query<H: AutomatonTrait<String>>(&self, hypothesis: &H) -> Option<String> {
+        let symbols = hypothesis.symbols().unwrap_or_default();
+        let separator = hypothesis.symbol_separator().unwrap_or_default();
+        let mut counterexamples = vec![];
+        let mut level: Vec<Vec<String>> = vec![vec![]];
+        for _ in 0..=self.max_length {
+            for word in &level {
+                let spelled = word.join(separator.as_str());
+                if self.inner.membership_query(&spelled) != hypothesis.accepts(word) {
+                    counterexamples.push(spelled);
+                }
+            }
+            level = level.iter()
+                .flat_map(|word| symbols.iter().map(move |symbol| [word.clone(), vec![symbol.clone()]].concat()))
+                .collect();
+        }
+        let query = self.equivalence_queries.fetch_add(1, Ordering::Relaxed);
+        match self.order {
+            CounterexampleOrder::Alternating if query % 2 == 1 => counterexamples.first().cloned(),
+            _ => counterexamples.last().cloned(),
+        }
+    }
+}
+
+// learns the pattern, turns the minimized DFA back into a pattern and compares both on sampled words
+async fn assert_learns(pattern: &str, alphabet: &[&str]) {
+    let minimal = learn(pattern, alphabet.to_vec()).await.minimize();
+    let (original, relearned) = (regex(pattern), regex(&minimal.to_regex()));
+
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..500 {
+        let length = rng.random_range(0..=16);
+        let word: String = (0..length).map(|_| alphabet[rng.random_range(0..alphabet.len())]).collect();
+        assert_eq!(original.matches(&word), relearned.matches(&word), "{pattern} and {} on {word}", minimal.to_regex());
+    }
+}
+
+
+#[tokio::test]
+async fn learns_a_language_over_multi_character_symbols() {
+    let dfa = learn("^(foo|bar|baz)*foo$", vec!["foo", "bar", "baz"]).await;
+
+    assert_eq!(dfa.state_count(), Some(2));
+    assert!(dfa.accepts(&symbols(&["foo"])));
+    assert!(dfa.accepts(&symbols(&["bar", "baz", "foo"])));
+    assert!(!dfa.accepts(&symbols(&["foo", "bar"])));
+    assert!(!dfa.accepts(&[]));
+}
+
+
+#[tokio::test]
+async fn learns_the_reverse_of_a_language() {
+    // a b* c is not its own reverse, which is c b* a
+    let dfa = learn_oracle(ReverseOracle::new(regex("^ab*c$")), vec!["a", "b", "c"]).await;
+
+    // the reference is a b* c with its transitions turned around, determinized
+    let reversed = NfaOracle::from_transitions(
+        HashSet::from([0, 1, 2]),
+        vec!['a', 'b', 'c'],
+        vec![(1, Some('a'), 0), (1, Some('b'), 1), (2, Some('c'), 1)],
+        HashSet::from([2]),
+        HashSet::from([0]),
+    ).unwrap();
+    let reference = reversed.determinize().unwrap();
+
+    assert_eq!(OracleTrait::equivalence_query(&reference, &dfa), None);
+    assert_eq!(dfa.state_count(), Some(4));
+    assert!(dfa.accepts(&symbols(&["c", "b", "b", "a"])));
+    assert!(!dfa.accepts(&symbols(&["a", "b", "c"])));
+}
+
+
+#[tokio::test]
+async fn reverses_words_of_multi_character_symbols() {
+    // reversing characters would turn foo into oof, the symbols have to stay whole
+    let oracle = ReverseOracle::new(regex("^foo(bar)*$")).with_symbols(symbols(&["foo", "bar"]), "");
+    let dfa = learn_oracle(oracle, vec!["foo", "bar"]).await;
+
+    assert_eq!(OracleTrait::equivalence_query(&regex("^(bar)*foo$"), &dfa), None);
+    assert!(dfa.accepts(&symbols(&["bar", "bar", "foo"])));
+    assert!(!dfa.accepts(&symbols(&["foo", "bar"])));
+}
+
+
+#[tokio::test]
+async fn learns_a_strictly_local_language() {
+    // no two consecutive a's, checked on windows of two symbols
+    let oracle = WindowOracle::new(vec!['a', 'b'], 2, |window| window != "aa").unwrap();
+    let dfa = learn_oracle(oracle, vec!["a", "b"]).await;
+
+    assert_eq!(OracleTrait::equivalence_query(&regex("^b*(ab+)*a?$"), &dfa), None);
+    assert_eq!(dfa.state_count(), Some(3));
+    assert!(dfa.accepts(&symbols(&["a", "b", "a"])));
+    assert!(!dfa.accepts(&symbols(&["b", "a", "a"])));
+}
+
+
+#[tokio::test]
+async fn learned_state_counts_match_the_myhill_nerode_classes() {
+    for pattern in ["^(ab)*$", "^a*b$", "^(a|b)*a(a|b)$"] {
+        let expected = myhill_nerode_classes(&regex(pattern), &['a', 'b'], 4);
+        let dfa = learn(pattern, vec!["a", "b"]).await;
+
+        assert_eq!(dfa.state_count(), Some(expected), "{pattern}");
+    }
+}
+
+
+#[tokio::test]
+async fn query_counts_grow_during_a_run() {
+    let (counter, counts) = QueryCounter::new(regex("^(a|b)*ab$"));
+    let mut calf = oracle_learner(counter, vec!["a", "b"]).await;
+    let initial_membership = counts.membership_count();
+    calf.run().await.expect("learning failed");
+
+    assert!(counts.membership_count() > initial_membership);
+    assert!(counts.equivalence_count() >= 1);
+    assert_eq!(counts.membership_count(), calf.stats().membership_queries);
+    assert_eq!(counts.equivalence_count(), calf.stats().equivalence_queries);
+}
+
+
+#[tokio::test]
+async fn learns_random_shallow_regexes() {
+    for seed in 0..8 {
+        let (pattern, oracle) = random_regex(&['a', 'b'], 2, seed);
+        let dfa = learn_oracle(oracle, vec!["a", "b"]).await;
+
+        // checked on longer words than the equivalence queries of the learner tried
+        let check = RandomWalkEquivalence::new(vec!['a', 'b']).with_seed(seed).with_length_range(0..=20);
+        assert_eq!(check.find_counterexample(|word| regex(&pattern).matches(word), &dfa), None, "{pattern}");
+    }
+}
+
+
+#[tokio::test]
+async fn learned_dfas_round_trip_through_a_regex() {
+    let cases: [(&str, &[&str]); 5] = [
+        ("^b*(ab*)(ab*ab*)*$", &["a", "b"]),
+        ("^(a|b)*abb$", &["a", "b"]),
+        ("^(ab|ba)*$", &["a", "b"]),
+        ("^a*b*c?$", &["a", "b", "c"]),
+        ("^(foo|bar)*foo$", &["foo", "bar"]),
+    ];
+    for (pattern, alphabet) in cases {
+        assert_learns(pattern, alphabet).await;
+    }
+}
+
+
+#[tokio::test]
+async fn converges_under_adversarial_counterexample_orders() {
+    for pattern in ["^(a|b)*ab$", "^(ab|ba)*$", "^b*(ab*ab*)*$"] {
+        let expected = myhill_nerode_classes(&regex(pattern), &['a', 'b'], 4);
+        for order in [CounterexampleOrder::LongestFirst, CounterexampleOrder::Alternating] {
+            for strategy in [CounterexampleStrategy::RivestSchapire, CounterexampleStrategy::AllPrefixes] {
+                let mut calf = oracle_learner(AdversarialOracle::new(pattern, order), vec!["a", "b"]).await;
+                calf.set_counterexample_strategy(strategy);
+                calf.run().await.expect("learning failed");
+                let dfa = calf.extract_automaton().await.expect("no hypothesis after learning");
+
+                assert_eq!(OracleTrait::equivalence_query(&regex(pattern), &dfa), None, "{pattern} {order:?} {strategy:?}");
+                assert_eq!(dfa.state_count(), Some(expected), "{pattern} {order:?} {strategy:?}");
+            }
+        }
+    }
+}
@@ -1,2 +1,14 @@
 pub mod regex_oracle;
-pub mod oracle_error;
\ No newline at end of file
+pub mod oracle_error;
+pub mod reverse_oracle;
+pub mod window_oracle;
+pub mod myhill_nerode;
+pub mod dfa_oracle;
+pub mod random_walk_equivalence;
+pub mod nfa_oracle;
+pub mod interactive_oracle;
+
+#[cfg(test)]
+mod learning_tests;
+#[cfg(test)]
+mod regex_fixtures;
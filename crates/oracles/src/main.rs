@@ -1,14 +1,13 @@
 use std::hash::Hash;
 use std::sync::Arc;
-use calf::calf::CALF;
+use calf::calf::{CALF, CalfBuilder};
 use oracles::regex_oracle::RegexOracle;
-use std::rc::Rc;
 use category_theory::core::base_category::BaseCategory;
 use category_theory::core::discrete_category::DiscreteCategory;
 use category_theory::core::dynamic_category::DynamicCategory;
 use category_theory::core::object_id::ObjectId;
 use category_theory::core::persistable_category::PersistableCategory;
-use category_theory::core::traits::category_trait::{CategoryTrait, CategoryFromObjects, CategoryCloneWithNewId};
+use category_theory::core::traits::category_trait::{CategoryTrait, CategoryCloneWithNewId};
 
 
 #[tokio::main]
@@ -26,15 +25,17 @@ where
     Category::Object: Clone + for<'a> From<&'a str> + From<String>,
     <Category::Object as CategoryTrait>::Object: Clone + for<'a> From<&'a str> + From<String> + From<ObjectId>,
 {
-    let allowed_alphabets = Category::from_objects(vec!["a", "b"]).await.unwrap();
     let regex_oracle = RegexOracle::new("^b*(ab*)(ab*ab*)*$".to_string())
         .expect("Failed to create regex oracle");
     // running sample regex oracle
-    let mut calf: CALF<RegexOracle, BaseCategory<Category>> = CALF::new(allowed_alphabets.into(), regex_oracle).await;
+    let mut calf: CALF<RegexOracle, BaseCategory<Category>> = CalfBuilder::new(regex_oracle)
+        .alphabet(vec!["a", "b"])
+        .export_path("/Users/mwas/projects/CALFrs/viz/data.json")
+        .build().await
+        .expect("Failed to create CALF");
 
+    calf.set_verbose(true);
     calf.run().await.unwrap();
 
-    calf.export_cytoscape("/Users/mwas/projects/CALFrs/viz/data.json").await.unwrap();
-
     println!("done running regex oracle with CALF");
 }
@@ -0,0 +1,47 @@
+use std::collections::HashSet;
+use calf::oracle_trait::OracleTrait;
+
+/// Estimates the number of Myhill-Nerode classes of the language of `oracle`, i.e. the number of
+/// states of its minimal complete DFA.
+///
+/// Two words are put in the same class when they agree on every suffix of length at most
+/// `max_length`, and only words of length at most `max_length` are considered, so the estimate
+/// is a lower bound that becomes exact once `max_length` is large enough for the language.
+pub fn myhill_nerode_classes<O: OracleTrait<String>>(
+    oracle: &O,
+    alphabet: &[char],
+    max_length: usize,
+) -> usize {
+    let words = shortlex_words(alphabet, max_length);
+    let residuals: HashSet<Vec<bool>> = words
+        .iter()
+        .map(|prefix| {
+            words
+                .iter()
+                .map(|suffix| oracle.membership_query(&format!("{prefix}{suffix}")))
+                .collect()
+        })
+        .collect();
+    residuals.len()
+}
+
+/// Returns all words over `alphabet` of length at most `max_length`, in shortlex order.
+fn shortlex_words(alphabet: &[char], max_length: usize) -> Vec<String> {
+    let mut alphabet = alphabet.to_vec();
+    alphabet.sort();
+    alphabet.dedup();
+
+    let mut words = vec![];
+    // all words of the current length, in lexicographic order
+    let mut level = vec![String::new()];
+    for length in 0..=max_length {
+        if length > 0 {
+            level = level
+                .iter()
+                .flat_map(|word| alphabet.iter().map(move |symbol| format!("{word}{symbol}")))
+                .collect();
+        }
+        words.extend(level.iter().cloned());
+    }
+    words
+}
@@ -0,0 +1,322 @@
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use calf::oracle_trait::{AutomatonTrait, OracleFailure, OracleTrait};
+use crate::dfa_oracle::{DfaOracle, StateId};
+use crate::oracle_error::OracleError;
+
+const DEFAULT_MAX_DETERMINIZED_STATES: usize = 4096;
+
+/// Oracle for the language of an NFA, possibly with epsilon moves.
+///
+/// Membership runs the subset construction lazily along the input only, equivalence determinizes
+/// the reachable part of the NFA up to a ceiling on the number of subset states.
+pub struct NfaOracle {
+    states: HashSet<StateId>,
+    alphabet: Vec<char>,
+    transitions: HashMap<(StateId, char), HashSet<StateId>>,
+    // moves taken without reading a symbol
+    epsilon_transitions: HashMap<StateId, HashSet<StateId>>,
+    initial: HashSet<StateId>,
+    accepting: HashSet<StateId>,
+    max_determinized_states: usize,
+}
+
+
+impl NfaOracle {
+    /// Creates a new `NfaOracle`, checking that all referenced states and symbols exist.
+    /// Missing transitions are allowed and lead to the empty set of states.
+    pub fn new(
+        states: HashSet<StateId>,
+        alphabet: Vec<char>,
+        transitions: HashMap<(StateId, char), HashSet<StateId>>,
+        initial: HashSet<StateId>,
+        accepting: HashSet<StateId>,
+    ) -> Result<Self, OracleError> {
+        if let Some(state) = initial.iter().find(|state| !states.contains(state)) {
+            return Err(OracleError::InvalidTransitionTable(format!("unknown initial state {state}")));
+        }
+        if let Some(state) = accepting.iter().find(|state| !states.contains(state)) {
+            return Err(OracleError::InvalidTransitionTable(format!("unknown accepting state {state}")));
+        }
+        for ((source, symbol), targets) in &transitions {
+            if !states.contains(source) {
+                return Err(OracleError::InvalidTransitionTable(
+                    format!("transition from unknown state {source} on {symbol}")));
+            }
+            if let Some(target) = targets.iter().find(|target| !states.contains(target)) {
+                return Err(OracleError::InvalidTransitionTable(
+                    format!("transition {source} --{symbol}--> {target} uses an unknown state")));
+            }
+            if !alphabet.contains(symbol) {
+                return Err(OracleError::InvalidTransitionTable(
+                    format!("transition from {source} uses an unknown symbol {symbol}")));
+            }
+        }
+        Ok(NfaOracle {
+            states,
+            alphabet,
+            transitions,
+            epsilon_transitions: HashMap::new(),
+            initial,
+            accepting,
+            max_determinized_states: DEFAULT_MAX_DETERMINIZED_STATES,
+        })
+    }
+
+    /// Creates a new `NfaOracle` from a list of `(source, symbol, target)` transitions,
+    /// where a `None` symbol is an epsilon move.
+    pub fn from_transitions(
+        states: HashSet<StateId>,
+        alphabet: Vec<char>,
+        transitions: Vec<(StateId, Option<char>, StateId)>,
+        initial: HashSet<StateId>,
+        accepting: HashSet<StateId>,
+    ) -> Result<Self, OracleError> {
+        let mut symbol_transitions: HashMap<(StateId, char), HashSet<StateId>> = HashMap::new();
+        let mut epsilon_transitions: HashMap<StateId, HashSet<StateId>> = HashMap::new();
+        for (source, symbol, target) in transitions {
+            match symbol {
+                Some(symbol) => symbol_transitions.entry((source, symbol)).or_default().insert(target),
+                None => epsilon_transitions.entry(source).or_default().insert(target),
+            };
+        }
+        Self::new(states, alphabet, symbol_transitions, initial, accepting)?
+            .with_epsilon_transitions(epsilon_transitions)
+    }
+
+    /// Adds epsilon moves, checking that all referenced states exist.
+    pub fn with_epsilon_transitions(
+        mut self,
+        epsilon_transitions: HashMap<StateId, HashSet<StateId>>,
+    ) -> Result<Self, OracleError> {
+        for (source, targets) in &epsilon_transitions {
+            if !self.states.contains(source) {
+                return Err(OracleError::InvalidTransitionTable(
+                    format!("epsilon transition from unknown state {source}")));
+            }
+            if let Some(target) = targets.iter().find(|target| !self.states.contains(target)) {
+                return Err(OracleError::InvalidTransitionTable(
+                    format!("epsilon transition {source} --> {target} uses an unknown state")));
+            }
+        }
+        self.epsilon_transitions = epsilon_transitions;
+        Ok(self)
+    }
+
+    /// Sets the number of subset states after which determinization gives up.
+    pub fn with_max_determinized_states(mut self, max_determinized_states: usize) -> Self {
+        self.max_determinized_states = max_determinized_states;
+        self
+    }
+
+    // adds every state reachable through epsilon moves
+    fn epsilon_closure(&self, mut current: BTreeSet<StateId>) -> BTreeSet<StateId> {
+        let mut pending: Vec<StateId> = current.iter().copied().collect();
+        while let Some(state) = pending.pop() {
+            for target in self.epsilon_transitions.get(&state).into_iter().flatten() {
+                if current.insert(*target) {
+                    pending.push(*target);
+                }
+            }
+        }
+        current
+    }
+
+    fn initial_states(&self) -> BTreeSet<StateId> {
+        self.epsilon_closure(self.initial.iter().copied().collect())
+    }
+
+    fn step(&self, current: &BTreeSet<StateId>, symbol: char) -> BTreeSet<StateId> {
+        self.epsilon_closure(current.iter()
+            .filter_map(|state| self.transitions.get(&(*state, symbol)))
+            .flatten()
+            .copied()
+            .collect())
+    }
+
+    fn is_accepting(&self, current: &BTreeSet<StateId>) -> bool {
+        current.iter().any(|state| self.accepting.contains(state))
+    }
+
+    /// Runs the input through the NFA, returning the set of states it can end up in.
+    pub fn run(&self, input: &str) -> BTreeSet<StateId> {
+        input.chars().fold(self.initial_states(), |current, symbol| self.step(&current, symbol))
+    }
+
+    /// Checks if the NFA accepts the input.
+    pub fn matches(&self, input: &str) -> bool {
+        self.is_accepting(&self.run(input))
+    }
+
+    /// Reachable part of the subset construction, subset states are numbered in discovery order.
+    ///
+    /// Fails when the construction would go past the state ceiling.
+    pub fn determinize(&self) -> Result<DfaOracle, OracleError> {
+        let initial = self.initial_states();
+        let mut ids = HashMap::from([(initial.clone(), 0)]);
+        let mut queue = VecDeque::from([initial]);
+        let mut transitions = HashMap::new();
+        let mut accepting = HashSet::new();
+
+        while let Some(current) = queue.pop_front() {
+            let id = ids[&current];
+            if self.is_accepting(&current) {
+                accepting.insert(id);
+            }
+            for symbol in &self.alphabet {
+                let next = self.step(&current, *symbol);
+                let next_id = match ids.get(&next) {
+                    Some(next_id) => *next_id,
+                    None => {
+                        if ids.len() >= self.max_determinized_states {
+                            return Err(OracleError::EquivalenceQueryFailed(format!(
+                                "determinization exceeds {} states", self.max_determinized_states)));
+                        }
+                        let next_id = ids.len();
+                        ids.insert(next.clone(), next_id);
+                        queue.push_back(next);
+                        next_id
+                    }
+                };
+                transitions.insert((id, *symbol), next_id);
+            }
+        }
+        DfaOracle::new((0..ids.len()).collect(), self.alphabet.clone(), transitions, 0, accepting)
+    }
+
+    /// Looks for a shortest word on which the hypothesis and the NFA disagree, searching the
+    /// product of the determinized NFA and the hypothesis.
+    ///
+    /// Fails with `OracleError::EquivalenceQueryFailed` if determinizing the NFA exceeds
+    /// the configured number of states.
+    pub fn determinized_counterexample<H: AutomatonTrait<String>>(&self, hypothesis: &H) -> Result<Option<String>, OracleFailure> {
+        OracleTrait::try_equivalence_query(&self.determinize()?, hypothesis)
+    }
+
+    /// Number of states of the NFA.
+    pub fn state_count(&self) -> usize {
+        self.states.len()
+    }
+}
+
+
+impl OracleTrait<String> for NfaOracle {
+    fn membership_query(&self, input: &String) -> bool {
+        self.matches(input)
+    }
+
+    /// When determinizing the NFA exceeds the configured number of states, words are tried up
+    /// to the search bound instead, so `None` only means that no short word tells them apart.
+    /// `CALF` asks `try_equivalence_query`, which reports the failure.
+    fn equivalence_query<H: AutomatonTrait<String>>(&self, hypothesis: &H) -> Option<String> {
+        self.determinized_counterexample(hypothesis)
+            .unwrap_or_else(|_| self.find_counterexample_by_enumeration(hypothesis, &self.alphabet))
+    }
+
+    fn try_equivalence_query<H: AutomatonTrait<String>>(&self, hypothesis: &H) -> Result<Option<String>, OracleFailure> {
+        self.determinized_counterexample(hypothesis)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use calf::learned_dfa::LearnedDfa;
+    use super::*;
+
+    // words over {a, b} whose n-th symbol from the end is an a, the classic NFA with an
+    // exponentially larger DFA
+    fn nth_last_is_a(n: usize) -> NfaOracle {
+        let mut transitions = vec![(0, Some('a'), 0), (0, Some('b'), 0), (0, Some('a'), 1)];
+        for state in 1..n {
+            transitions.push((state, Some('a'), state + 1));
+            transitions.push((state, Some('b'), state + 1));
+        }
+        NfaOracle::from_transitions((0..=n).collect(), vec!['a', 'b'], transitions, HashSet::from([0]), HashSet::from([n])).unwrap()
+    }
+
+    // accepts every word
+    fn universal() -> LearnedDfa {
+        let state = "q".to_string();
+        let transitions = HashMap::from([
+            ((state.clone(), "a".to_string()), state.clone()),
+            ((state.clone(), "b".to_string()), state.clone()),
+        ]);
+        LearnedDfa::new(HashSet::from([state.clone()]), vec!["a".to_string(), "b".to_string()], transitions, state.clone(), HashSet::from([state]))
+    }
+
+    // the last symbol is an a
+    fn last_is_a() -> LearnedDfa {
+        let (other, seen_a) = ("other".to_string(), "a".to_string());
+        let transitions = HashMap::from([
+            ((other.clone(), "a".to_string()), seen_a.clone()),
+            ((other.clone(), "b".to_string()), other.clone()),
+            ((seen_a.clone(), "a".to_string()), seen_a.clone()),
+            ((seen_a.clone(), "b".to_string()), other.clone()),
+        ]);
+        LearnedDfa::new(HashSet::from([other.clone(), seen_a.clone()]), vec!["a".to_string(), "b".to_string()], transitions, other, HashSet::from([seen_a]))
+    }
+
+    #[test]
+    fn equivalence_searches_the_product_with_the_hypothesis() {
+        let oracle = nth_last_is_a(1);
+        assert_eq!(OracleTrait::equivalence_query(&oracle, &last_is_a()), None);
+        assert_eq!(OracleTrait::equivalence_query(&oracle, &universal()), Some(String::new()));
+        assert_eq!(OracleTrait::equivalence_query(&nth_last_is_a(3), &last_is_a()), Some("a".to_string()));
+    }
+
+    // a+ over {a, b}, reading each a after a chain of epsilon moves 0 -> 1 -> 2 that cycles
+    // back to 0, and returning to the start of the chain after it
+    fn epsilon_a_plus() -> NfaOracle {
+        let transitions = vec![(0, None, 1), (1, None, 2), (2, None, 0), (2, Some('a'), 3), (3, None, 0)];
+        NfaOracle::from_transitions((0..4).collect(), vec!['a', 'b'], transitions, HashSet::from([0]), HashSet::from([3])).unwrap()
+    }
+
+    #[test]
+    fn membership_follows_epsilon_chains_and_cycles() {
+        let oracle = epsilon_a_plus();
+        assert_eq!(oracle.run(""), BTreeSet::from([0, 1, 2]));
+        assert!(oracle.matches("a"));
+        assert!(oracle.matches("aaa"));
+        assert!(!oracle.matches(""));
+        assert!(!oracle.matches("ab"));
+    }
+
+    #[test]
+    fn equivalence_follows_epsilon_moves() {
+        let name = |name: &str| name.to_string();
+        let transitions = [("e", "a", "m"), ("e", "b", "x"), ("m", "a", "m"), ("m", "b", "x"), ("x", "a", "x"), ("x", "b", "x")]
+            .iter()
+            .map(|(source, symbol, target)| ((name(source), name(symbol)), name(target)))
+            .collect();
+        let a_plus = LearnedDfa::new(
+            HashSet::from([name("e"), name("m"), name("x")]), vec![name("a"), name("b")], transitions, name("e"), HashSet::from([name("m")]));
+
+        assert_eq!(OracleTrait::equivalence_query(&epsilon_a_plus(), &a_plus), None);
+        assert_eq!(OracleTrait::equivalence_query(&epsilon_a_plus(), &universal()), Some(String::new()));
+        assert_eq!(OracleTrait::equivalence_query(&epsilon_a_plus(), &last_is_a()), Some("ba".to_string()));
+    }
+
+    #[test]
+    fn epsilon_transitions_with_unknown_states_are_rejected() {
+        let result = NfaOracle::from_transitions(
+            HashSet::from([0, 1]), vec!['a'], vec![(0, Some('a'), 1), (1, None, 5)], HashSet::from([0]), HashSet::from([1]));
+        assert!(matches!(result, Err(OracleError::InvalidTransitionTable(_))));
+
+        let from_unknown = nth_last_is_a(1).with_epsilon_transitions(HashMap::from([(7, HashSet::from([0]))]));
+        assert!(matches!(from_unknown, Err(OracleError::InvalidTransitionTable(_))));
+    }
+
+    #[test]
+    fn failed_determinization_is_reported_not_taken_as_equivalence() {
+        let oracle = nth_last_is_a(6).with_max_determinized_states(8);
+        let result = OracleTrait::try_equivalence_query(&oracle, &last_is_a());
+        assert!(result.unwrap_err().to_string().contains("determinization exceeds 8 states"));
+    }
+
+    #[test]
+    fn equivalence_query_enumerates_words_past_the_ceiling() {
+        let oracle = nth_last_is_a(6).with_max_determinized_states(8);
+        assert_eq!(OracleTrait::equivalence_query(&oracle, &last_is_a()), Some("a".to_string()));
+        assert_eq!(OracleTrait::equivalence_query(&nth_last_is_a(1).with_max_determinized_states(1), &last_is_a()), None);
+    }
+}
@@ -1,9 +1,32 @@
 
 
+use std::fmt;
+
 #[derive(Debug)]
 pub enum OracleError {
     InvalidRegexPattern(String),
+    InvalidWindowSize(usize),
+    InvalidTransitionTable(String),
     MembershipQueryFailed(String),
     EquivalenceQueryFailed(String),
+    AlphabetNotInferable(String),
+    InvalidDotFile(String),
     UnknownError,
-}
\ No newline at end of file
+}
+
+impl fmt::Display for OracleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OracleError::InvalidRegexPattern(pattern) => write!(f, "invalid regex pattern {}", pattern),
+            OracleError::InvalidWindowSize(window) => write!(f, "invalid window size {}", window),
+            OracleError::InvalidTransitionTable(reason) => write!(f, "invalid transition table: {}", reason),
+            OracleError::MembershipQueryFailed(reason) => write!(f, "membership query failed: {}", reason),
+            OracleError::EquivalenceQueryFailed(reason) => write!(f, "equivalence query failed: {}", reason),
+            OracleError::AlphabetNotInferable(reason) => write!(f, "alphabet not inferable from the pattern: {}, give one", reason),
+            OracleError::InvalidDotFile(reason) => write!(f, "invalid DOT file: {}", reason),
+            OracleError::UnknownError => write!(f, "unknown oracle error"),
+        }
+    }
+}
+
+impl std::error::Error for OracleError {}
@@ -0,0 +1,226 @@
+use std::ops::RangeInclusive;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use calf::oracle_trait::AutomatonTrait;
+
+const DEFAULT_SAMPLE_COUNT: usize = 1000;
+const DEFAULT_MAX_LENGTH: usize = 16;
+
+/// Distribution of the words sampled by `RandomWalkEquivalence`. A statistical equivalence check
+/// only bounds the error on words drawn from its distribution, so it should be the one the
+/// learned automaton is used on.
+///
+/// Symbols are drawn from the alphabet given on each sample, the one of the hypothesis, so a
+/// distribution only shapes which words of it come up.
+pub trait WordDistribution {
+    /// Samples a word over `alphabet`, as its symbols. `alphabet` is not empty.
+    fn sample(&self, rng: &mut dyn RngCore, alphabet: &[String]) -> Vec<String>;
+
+    /// Whether the distribution has no word to sample, then nothing is checked.
+    fn is_empty(&self) -> bool {
+        false
+    }
+}
+
+/// Words whose length is uniform over a range, each symbol uniform over the alphabet.
+pub struct UniformLength {
+    lengths: RangeInclusive<usize>,
+}
+
+impl UniformLength {
+    pub fn new(lengths: RangeInclusive<usize>) -> Self {
+        UniformLength { lengths }
+    }
+}
+
+impl WordDistribution for UniformLength {
+    fn sample(&self, rng: &mut dyn RngCore, alphabet: &[String]) -> Vec<String> {
+        let length = rng.random_range(self.lengths.clone());
+        sample_symbols(rng, alphabet, length)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.lengths.is_empty()
+    }
+}
+
+/// Words ended after each symbol with a fixed probability, so a word has length n with
+/// probability `(1 - stop)^n * stop` and short words are the most likely. Each symbol is uniform
+/// over the alphabet.
+pub struct GeometricLength {
+    stop_probability: f64,
+}
+
+impl GeometricLength {
+    /// Panics unless `stop_probability` is in (0, 1].
+    pub fn new(stop_probability: f64) -> Self {
+        assert!(stop_probability > 0.0 && stop_probability <= 1.0, "stop probability {stop_probability} is not in (0, 1]");
+        GeometricLength { stop_probability }
+    }
+}
+
+impl WordDistribution for GeometricLength {
+    fn sample(&self, rng: &mut dyn RngCore, alphabet: &[String]) -> Vec<String> {
+        let mut length = 0;
+        while !rng.random_bool(self.stop_probability) {
+            length += 1;
+        }
+        sample_symbols(rng, alphabet, length)
+    }
+}
+
+fn sample_symbols(rng: &mut dyn RngCore, alphabet: &[String], length: usize) -> Vec<String> {
+    (0..length).map(|_| alphabet[rng.random_range(0..alphabet.len())].clone()).collect()
+}
+
+
+/// Statistical equivalence check comparing a membership function and a hypothesis on random words.
+///
+/// Every search starts from the same seed, so a given configuration always samples the same words.
+pub struct RandomWalkEquivalence {
+    alphabet: Vec<char>,
+    seed: u64,
+    sample_count: usize,
+    distribution: Box<dyn WordDistribution + Send + Sync>,
+}
+
+
+impl RandomWalkEquivalence {
+    /// Creates a new `RandomWalkEquivalence` sampling words over `alphabet`.
+    pub fn new(alphabet: Vec<char>) -> Self {
+        RandomWalkEquivalence {
+            alphabet,
+            seed: 0,
+            sample_count: DEFAULT_SAMPLE_COUNT,
+            distribution: Box::new(UniformLength::new(0..=DEFAULT_MAX_LENGTH)),
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the number of words sampled by each search.
+    pub fn with_sample_count(mut self, sample_count: usize) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+
+    /// Sets the range the length of each sampled word is drawn uniformly from.
+    pub fn with_length_range(self, length_range: RangeInclusive<usize>) -> Self {
+        self.with_distribution(UniformLength::new(length_range))
+    }
+
+    /// Sets the distribution words are sampled from, uniform over lengths up to 16 by default.
+    pub fn with_distribution(mut self, distribution: impl WordDistribution + Send + Sync + 'static) -> Self {
+        self.distribution = Box::new(distribution);
+        self
+    }
+
+    /// Returns the first sampled word on which `membership` and `hypothesis` disagree.
+    ///
+    /// Words are sampled over the hypothesis' symbols when it reports them, and over the
+    /// single characters of the alphabet otherwise, and spelled with the hypothesis' separator.
+    pub fn find_counterexample<H: AutomatonTrait<String>>(
+        &self,
+        membership: impl Fn(&str) -> bool,
+        hypothesis: &H,
+    ) -> Option<String> {
+        if self.distribution.is_empty() {
+            return None;
+        }
+        let alphabet = hypothesis.symbols()
+            .unwrap_or_else(|| self.alphabet.iter().map(|symbol| symbol.to_string()).collect());
+        let separator = hypothesis.symbol_separator().unwrap_or_default();
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        for _ in 0..self.sample_count {
+            let symbols = if alphabet.is_empty() { vec![] } else { self.distribution.sample(&mut rng, &alphabet) };
+            let word = symbols.join(&separator);
+            if membership(&word) != hypothesis.accepts(&symbols) {
+                return Some(word);
+            }
+        }
+        None
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+    use calf::learned_dfa::LearnedDfa;
+    use super::*;
+
+    // accepts every word over {a, b}
+    fn universal() -> LearnedDfa {
+        let state = "q".to_string();
+        let transitions = HashMap::from([
+            ((state.clone(), "a".to_string()), state.clone()),
+            ((state.clone(), "b".to_string()), state.clone()),
+        ]);
+        LearnedDfa::new(HashSet::from([state.clone()]), vec!["a".to_string(), "b".to_string()], transitions, state.clone(), HashSet::from([state]))
+    }
+
+    fn no_bb(word: &str) -> bool {
+        !word.contains("bb")
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_counterexample() {
+        let walk = |seed| RandomWalkEquivalence::new(vec!['a', 'b']).with_seed(seed).find_counterexample(no_bb, &universal());
+        let counterexample = walk(7);
+
+        assert!(counterexample.is_some());
+        assert_eq!(walk(7), counterexample);
+        let seeds_with_other_counterexamples = (0..20).filter(|seed| walk(*seed) != counterexample).count();
+        assert!(seeds_with_other_counterexamples > 0);
+    }
+
+    #[test]
+    fn finds_a_known_disagreement() {
+        let walk = RandomWalkEquivalence::new(vec!['a', 'b']).with_length_range(2..=6);
+        let counterexample = walk.find_counterexample(no_bb, &universal()).unwrap();
+
+        assert!(counterexample.contains("bb"));
+        assert_eq!(walk.find_counterexample(|_| true, &universal()), None);
+        assert_eq!(walk.with_length_range(0..=1).find_counterexample(no_bb, &universal()), None);
+    }
+
+    // the share of each length among `samples` words of the distribution
+    fn length_shares(distribution: &impl WordDistribution, samples: usize) -> HashMap<usize, f64> {
+        let alphabet = vec!["a".to_string(), "bc".to_string()];
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut counts = HashMap::new();
+        for _ in 0..samples {
+            let word = distribution.sample(&mut rng, &alphabet);
+            assert!(word.iter().all(|symbol| alphabet.contains(symbol)));
+            *counts.entry(word.len()).or_insert(0) += 1;
+        }
+        counts.into_iter().map(|(length, count)| (length, count as f64 / samples as f64)).collect()
+    }
+
+    #[test]
+    fn sampled_lengths_follow_the_distribution() {
+        let uniform = length_shares(&UniformLength::new(2..=5), 20000);
+        assert_eq!(uniform.len(), 4);
+        for length in 2..=5 {
+            assert!((uniform[&length] - 0.25).abs() < 0.02, "{length}: {}", uniform[&length]);
+        }
+
+        let geometric = length_shares(&GeometricLength::new(0.5), 20000);
+        for (length, expected) in [(0, 0.5), (1, 0.25), (2, 0.125), (3, 0.0625)] {
+            assert!((geometric[&length] - expected).abs() < 0.02, "{length}: {}", geometric[&length]);
+        }
+        assert_eq!(length_shares(&GeometricLength::new(1.0), 100), HashMap::from([(0, 1.0)]));
+    }
+
+    #[test]
+    fn the_walk_samples_from_its_distribution() {
+        // only the empty word and single symbols, neither contains bb
+        let short = RandomWalkEquivalence::new(vec!['a', 'b']).with_distribution(UniformLength::new(0..=1));
+        assert_eq!(short.find_counterexample(no_bb, &universal()), None);
+        let geometric = RandomWalkEquivalence::new(vec!['a', 'b']).with_distribution(GeometricLength::new(0.2));
+        assert!(geometric.find_counterexample(no_bb, &universal()).unwrap().contains("bb"));
+    }
+}
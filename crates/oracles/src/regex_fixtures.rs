@@ -0,0 +1,56 @@
+//! Random regular expressions over a given alphabet, for tests learning many languages.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::regex_oracle::RegexOracle;
+
+/// Returns an anchored random pattern over `alphabet` nested at most `max_depth` deep, with its oracle.
+///
+/// The same seed always gives the same pattern. The oracle searches counterexamples over the whole
+/// alphabet, including the symbols the pattern happens not to use.
+pub(crate) fn random_regex(alphabet: &[char], max_depth: usize, seed: u64) -> (String, RegexOracle) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let pattern = format!("^{}$", expression(&mut rng, alphabet, max_depth));
+    let oracle = RegexOracle::builder()
+        .pattern(pattern.clone())
+        .alphabet(alphabet.to_vec())
+        .build()
+        .expect("generated pattern does not compile");
+    (pattern, oracle)
+}
+
+fn expression(rng: &mut StdRng, alphabet: &[char], depth: usize) -> String {
+    if depth == 0 || rng.random_bool(0.3) {
+        let symbol = alphabet[rng.random_range(0..alphabet.len())];
+        return regex::escape(&symbol.to_string());
+    }
+    // every operand is grouped, so the operators never need precedence
+    match rng.random_range(0..3) {
+        0 => format!("(?:{})(?:{})", expression(rng, alphabet, depth - 1), expression(rng, alphabet, depth - 1)),
+        1 => format!("(?:{}|{})", expression(rng, alphabet, depth - 1), expression(rng, alphabet, depth - 1)),
+        _ => format!("(?:{})*", expression(rng, alphabet, depth - 1)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_patterns_compile_and_depend_only_on_the_seed() {
+        for seed in 0..100 {
+            let (pattern, _) = random_regex(&['a', 'b', '.'], 4, seed);
+            assert_eq!(random_regex(&['a', 'b', '.'], 4, seed).0, pattern);
+            assert!(pattern.starts_with('^') && pattern.ends_with('$'));
+        }
+    }
+
+    #[test]
+    fn depth_zero_gives_a_single_symbol() {
+        let (pattern, oracle) = random_regex(&['a'], 0, 7);
+        assert_eq!(pattern, "^a$");
+        assert!(oracle.matches("a"));
+        assert!(!oracle.matches("aa"));
+    }
+}
@@ -1,26 +1,192 @@
+use std::collections::{BTreeSet, HashSet, VecDeque};
 use regex::Regex;
+use regex_automata::Anchored;
+use regex_automata::dfa::{dense, Automaton};
+use regex_automata::util::primitives::StateID;
+use regex_automata::util::start;
+use regex_syntax::hir::{Class, Hir, HirKind, Literal};
 use calf::oracle_trait::{AutomatonTrait, OracleTrait};
 use crate::oracle_error::OracleError;
 
+// largest class of the pattern taken into an inferred alphabet
+const MAX_INFERRED_CLASS_SIZE: u32 = 64;
+
 pub struct RegexOracle {
     regex: Regex,
+    // the pattern as a DFA over bytes with its unanchored start state, None for patterns without
+    // one e.g. with Unicode word boundaries
+    dfa: Option<(dense::DFA<Vec<u32>>, StateID)>,
+    // symbols used to build candidate counterexamples when the product can't be explored
+    alphabet: Vec<char>,
+    max_counterexample_length: Option<usize>,
+}
+
+/// Builder for a `RegexOracle` with a configured equivalence-query search.
+#[derive(Default)]
+pub struct RegexOracleBuilder {
+    pattern: Option<String>,
+    alphabet: Option<Vec<char>>,
+    max_counterexample_length: Option<usize>,
 }
 
 
 
 impl RegexOracle {
     /// Creates a new `RegexOracle` with the given regex pattern.
+    ///
+    /// The alphabet used by equivalence queries is the set of characters the pattern can match,
+    /// so patterns with large classes such as `.` need `builder().alphabet(...)` instead.
     pub fn new(regex_string: String) -> Result<Self, OracleError> {
-        match Regex::new(&regex_string) {
-            Ok(regex) => Ok(RegexOracle { regex }),
-            Err(_) => Err(OracleError::InvalidRegexPattern(regex_string)),
-        }
+        Self::builder().pattern(regex_string).build()
+    }
+
+    /// Returns a builder to configure the alphabet and search depth of equivalence queries.
+    pub fn builder() -> RegexOracleBuilder {
+        RegexOracleBuilder::default()
     }
 
     /// Checks if the input string matches the regex pattern.
     pub fn matches(&self, input: &str) -> bool {
         self.regex.is_match(input)
     }
+
+    // explores the product of the pattern's DFA and the hypothesis breadth first as DfaOracle
+    // does, so the counterexample is a shortest one and the search is exhaustive. the pattern
+    // matches anywhere in a word as `is_match` does, so a state of the product also records
+    // whether a match was seen, after which the DFA state no longer matters and is reset.
+    fn product_counterexample<H: AutomatonTrait<String>>(
+        &self,
+        (dfa, start): &(dense::DFA<Vec<u32>>, StateID),
+        hypothesis: &H,
+        initial: String,
+    ) -> Option<String> {
+        let mut symbols = hypothesis.symbols()
+            .unwrap_or_else(|| self.alphabet.iter().map(|symbol| symbol.to_string()).collect());
+        symbols.sort();
+        symbols.dedup();
+        let separator = hypothesis.symbol_separator().unwrap_or_default();
+
+        let read = |(state, matched): (StateID, bool), spelled: &str| {
+            spelled.bytes().fold((state, matched), |(state, matched), byte| match matched {
+                true => (*start, true),
+                false => {
+                    let next = dfa.next_state(state, byte);
+                    // matches are reported one byte late, so this one ends before the byte
+                    (next, dfa.is_match_state(next))
+                },
+            })
+        };
+
+        // as in DfaOracle None is the sink of a missing transition of the hypothesis, and the
+        // root is not marked as visited since words continuing from it take no separator
+        let mut visited: HashSet<((StateID, bool), Option<String>)> = HashSet::new();
+        let mut queue = VecDeque::from([(((*start, false), Some(initial)), String::new())]);
+        while let Some(((state, hypothesis_state), word)) = queue.pop_front() {
+            let accepted = state.1 || dfa.is_match_state(dfa.next_eoi_state(state.0));
+            let hypothesis_accepted = hypothesis_state.as_deref()
+                .is_some_and(|hypothesis_state| hypothesis.is_accepting_state(hypothesis_state));
+            if accepted != hypothesis_accepted {
+                return Some(word);
+            }
+            for symbol in &symbols {
+                let spelled = match word.is_empty() {
+                    true => symbol.clone(),
+                    false => format!("{separator}{symbol}"),
+                };
+                let next = (
+                    read(state, &spelled),
+                    hypothesis_state.as_deref().and_then(|hypothesis_state| hypothesis.transition(hypothesis_state, symbol)),
+                );
+                if visited.insert(next.clone()) {
+                    queue.push_back((next, format!("{word}{spelled}")));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl RegexOracleBuilder {
+    /// Sets the regex pattern defining the language.
+    pub fn pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.pattern = Some(pattern.into());
+        self
+    }
+
+    /// Sets the symbols used to build candidate counterexamples when the hypothesis has none,
+    /// by default the characters the pattern can match.
+    pub fn alphabet(mut self, alphabet: Vec<char>) -> Self {
+        self.alphabet = Some(alphabet);
+        self
+    }
+
+    /// Sets the length of the longest word tried by equivalence queries that enumerate words,
+    /// by default derived from the size of the hypothesis.
+    pub fn max_counterexample_length(mut self, max_counterexample_length: usize) -> Self {
+        self.max_counterexample_length = Some(max_counterexample_length);
+        self
+    }
+
+    pub fn build(self) -> Result<RegexOracle, OracleError> {
+        let pattern = self.pattern.unwrap_or_default();
+        let regex = Regex::new(&pattern).map_err(|_| OracleError::InvalidRegexPattern(pattern.clone()))?;
+        let mut alphabet = match self.alphabet {
+            Some(alphabet) => alphabet,
+            None => pattern_alphabet(&pattern)?,
+        };
+        alphabet.sort();
+        alphabet.dedup();
+        let unanchored = start::Config::new().anchored(Anchored::No);
+        let dfa = dense::DFA::new(&pattern).ok()
+            .and_then(|dfa| dfa.start_state(&unanchored).ok().map(|start| (dfa, start)));
+        Ok(RegexOracle {
+            alphabet,
+            regex,
+            dfa,
+            max_counterexample_length: self.max_counterexample_length,
+        })
+    }
+}
+
+/// Returns the sorted characters the pattern can match, read off its syntax tree.
+///
+/// Fails for classes too large to serve as an alphabet, e.g. `.` or `\w`, which need an explicit one.
+fn pattern_alphabet(pattern: &str) -> Result<Vec<char>, OracleError> {
+    let hir = regex_syntax::parse(pattern).map_err(|_| OracleError::InvalidRegexPattern(pattern.to_string()))?;
+    let mut characters = BTreeSet::new();
+    collect_characters(&hir, &mut characters)?;
+    Ok(characters.into_iter().collect())
+}
+
+fn collect_characters(hir: &Hir, characters: &mut BTreeSet<char>) -> Result<(), OracleError> {
+    match hir.kind() {
+        // anchors and word boundaries match no character
+        HirKind::Empty | HirKind::Look(_) => Ok(()),
+        HirKind::Literal(Literal(bytes)) => {
+            let literal = std::str::from_utf8(bytes)
+                .map_err(|_| OracleError::AlphabetNotInferable("pattern matches bytes".to_string()))?;
+            characters.extend(literal.chars());
+            Ok(())
+        },
+        HirKind::Class(Class::Unicode(class)) => {
+            let size: u32 = class.ranges().iter()
+                .map(|range| u32::from(range.end()) - u32::from(range.start()) + 1)
+                .sum();
+            if size > MAX_INFERRED_CLASS_SIZE {
+                return Err(OracleError::AlphabetNotInferable(format!("class of {size} characters")));
+            }
+            for range in class.ranges() {
+                characters.extend(range.start()..=range.end());
+            }
+            Ok(())
+        },
+        HirKind::Class(Class::Bytes(_)) => Err(OracleError::AlphabetNotInferable("pattern matches bytes".to_string())),
+        HirKind::Repetition(repetition) => collect_characters(&repetition.sub, characters),
+        HirKind::Capture(capture) => collect_characters(&capture.sub, characters),
+        HirKind::Concat(hirs) | HirKind::Alternation(hirs) => {
+            hirs.iter().try_for_each(|hir| collect_characters(hir, characters))
+        },
+    }
 }
 
 
@@ -30,6 +196,90 @@ impl OracleTrait<String> for RegexOracle {
     }
 
     fn equivalence_query<H: AutomatonTrait<String>>(&self, hypothesis: &H) -> Option<String> {
-        todo!()
+        match (&self.dfa, hypothesis.initial_state()) {
+            (Some(dfa), Some(initial)) => self.product_counterexample(dfa, hypothesis, initial),
+            // words are tried in shortlex order up to the search bound, so the counterexample is
+            // a shortest one and the same one on every run, but None only means none that short
+            _ => self.find_counterexample_by_enumeration(hypothesis, &self.alphabet),
+        }
+    }
+
+    fn search_bound(&self) -> Option<usize> {
+        self.max_counterexample_length
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+    use calf::learned_dfa::LearnedDfa;
+    use super::*;
+
+    // one accepting state looping on every symbol
+    fn star_of(symbols: &[&str]) -> LearnedDfa {
+        let state = "q".to_string();
+        let alphabet: Vec<String> = symbols.iter().map(|symbol| symbol.to_string()).collect();
+        let transitions = alphabet.iter()
+            .map(|symbol| ((state.clone(), symbol.clone()), state.clone()))
+            .collect::<HashMap<_, _>>();
+        LearnedDfa::new(HashSet::from([state.clone()]), alphabet, transitions, state.clone(), HashSet::from([state]))
+    }
+
+    fn alphabet_of(pattern: &str) -> Vec<char> {
+        RegexOracle::new(pattern.to_string()).unwrap().alphabet
+    }
+
+    #[test]
+    fn alphabet_is_read_off_the_pattern() {
+        assert_eq!(alphabet_of("^[a-c]+$"), vec!['a', 'b', 'c']);
+        assert_eq!(alphabet_of(r"^x\.y$"), vec!['.', 'x', 'y']);
+        assert_eq!(alphabet_of("^(?P<name>a){2,3}(?i:b)$"), vec!['B', 'a', 'b']);
+        assert_eq!(alphabet_of(r"^\bab*$"), vec!['a', 'b']);
+    }
+
+    #[test]
+    fn large_classes_need_an_explicit_alphabet() {
+        assert!(matches!(RegexOracle::new("^a.*$".to_string()), Err(OracleError::AlphabetNotInferable(_))));
+        let oracle = RegexOracle::builder().pattern("^a.*$").alphabet(vec!['b', 'a']).build().unwrap();
+        assert_eq!(oracle.alphabet, vec!['a', 'b']);
+    }
+
+    #[test]
+    fn equivalence_reads_words_as_the_hypothesis_symbols() {
+        let oracle = RegexOracle::new("^(foo|bar)*$".to_string()).unwrap();
+        assert_eq!(OracleTrait::equivalence_query(&oracle, &star_of(&["foo", "bar"])), None);
+        assert_eq!(OracleTrait::equivalence_query(&oracle, &star_of(&["foo", "bar", "baz"])), Some("baz".to_string()));
+    }
+
+    #[test]
+    fn equivalence_finds_counterexamples_past_the_search_bound() {
+        // a one state hypothesis is searched up to three symbols when words are enumerated
+        let oracle = RegexOracle::new("^a{0,11}$".to_string()).unwrap();
+        assert_eq!(OracleTrait::equivalence_query(&oracle, &star_of(&["a"])), Some("a".repeat(12)));
+    }
+
+    #[test]
+    fn equivalence_matches_anywhere_in_the_word_as_membership_does() {
+        let oracle = RegexOracle::new("ab".to_string()).unwrap();
+        assert!(oracle.matches("bbaba"));
+        assert_eq!(OracleTrait::equivalence_query(&oracle, &star_of(&["a", "b"])), Some(String::new()));
+        assert_eq!(OracleTrait::equivalence_query(&oracle, &star_of(&["ab"])), Some(String::new()));
+
+        // (ab)+ agrees with it over the single symbol ab
+        let mut transitions = HashMap::new();
+        for (source, target) in [("e", "m"), ("m", "m")] {
+            transitions.insert((source.to_string(), "ab".to_string()), target.to_string());
+        }
+        let ab_plus = LearnedDfa::new(
+            HashSet::from(["e".to_string(), "m".to_string()]), vec!["ab".to_string()], transitions, "e".to_string(), HashSet::from(["m".to_string()]));
+        assert_eq!(OracleTrait::equivalence_query(&oracle, &ab_plus), None);
+    }
+
+    #[test]
+    fn patterns_without_a_dfa_fall_back_to_enumeration() {
+        let oracle = RegexOracle::new(r"^\bab*$".to_string()).unwrap();
+        assert!(oracle.dfa.is_none());
+        assert_eq!(OracleTrait::equivalence_query(&oracle, &star_of(&["a", "b"])), Some(String::new()));
+    }
+}
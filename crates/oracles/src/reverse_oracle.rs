@@ -0,0 +1,206 @@
+use std::collections::BTreeSet;
+use calf::calf::split_into_symbols;
+use calf::oracle_trait::{AutomatonTrait, OracleFailure, OracleTrait};
+
+/// Oracle for the reverse of the language of another oracle.
+///
+/// Words are reversed symbol by symbol, a symbol of several characters is kept as it is. By default
+/// the symbols are single characters, `with_symbols` sets the alphabet and separator of the learner.
+pub struct ReverseOracle<O: OracleTrait<String>> {
+    inner: O,
+    // None for single characters
+    symbols: Option<Vec<String>>,
+    separator: String,
+}
+
+/// Presents a hypothesis for the reversed language as a hypothesis for the inner language.
+///
+/// When the hypothesis exposes its states the reversal is explored as a DFA by the subset
+/// construction, so an inner oracle can search the product instead of enumerating words.
+struct ReversedAutomaton<'a, H> {
+    hypothesis: &'a H,
+    // the states of the hypothesis reachable from its initial state, sorted. a state of the
+    // reversal is a subset of them written as one 0 or 1 per state.
+    states: Option<Vec<String>>,
+}
+
+
+impl<O: OracleTrait<String>> ReverseOracle<O> {
+    /// Creates a new `ReverseOracle` for the reverse of the language of `inner`.
+    pub fn new(inner: O) -> Self {
+        ReverseOracle { inner, symbols: None, separator: String::new() }
+    }
+
+    /// Sets the symbols words are made of and the separator put between them.
+    pub fn with_symbols(mut self, symbols: Vec<String>, separator: &str) -> Self {
+        self.symbols = Some(symbols);
+        self.separator = separator.to_string();
+        self
+    }
+
+    /// Returns the wrapped oracle.
+    pub fn into_inner(self) -> O {
+        self.inner
+    }
+}
+
+/// Reverses the sequence of symbols of a word spelled with `separator` between them,
+/// `None` if the word is not made of the symbols.
+fn reverse(word: &str, symbols: Option<&[String]>, separator: &str) -> Option<String> {
+    let mut split: Vec<String> = match (symbols, separator) {
+        (None, "") => word.chars().map(String::from).collect(),
+        (None, separator) => word.split(separator).filter(|symbol| !symbol.is_empty()).map(String::from).collect(),
+        (Some(symbols), "") => split_into_symbols(word, symbols)?,
+        (Some(symbols), separator) => word.split(separator)
+            .filter(|symbol| !symbol.is_empty())
+            .map(|symbol| symbols.iter().find(|known| *known == symbol).cloned())
+            .collect::<Option<_>>()?,
+    };
+    split.reverse();
+    Some(split.join(separator))
+}
+
+
+impl<'a, H: AutomatonTrait<String>> ReversedAutomaton<'a, H> {
+    fn new(hypothesis: &'a H) -> Self {
+        let states = hypothesis.initial_state().zip(hypothesis.symbols()).map(|(initial, symbols)| {
+            let mut reachable = BTreeSet::from([initial.clone()]);
+            let mut pending = vec![initial];
+            while let Some(state) = pending.pop() {
+                for target in symbols.iter().filter_map(|symbol| hypothesis.transition(&state, symbol)) {
+                    if reachable.insert(target.clone()) {
+                        pending.push(target);
+                    }
+                }
+            }
+            reachable.into_iter().collect()
+        });
+        ReversedAutomaton { hypothesis, states }
+    }
+
+    fn subset(&self, states: &[String], contains: impl Fn(&String) -> bool) -> String {
+        states.iter().map(|state| if contains(state) { '1' } else { '0' }).collect()
+    }
+}
+
+
+impl<'a, H: AutomatonTrait<String>> AutomatonTrait<String> for ReversedAutomaton<'a, H> {
+    fn accepts(&self, word: &[String]) -> bool {
+        let reversed: Vec<String> = word.iter().rev().cloned().collect();
+        self.hypothesis.accepts(&reversed)
+    }
+
+    fn symbols(&self) -> Option<Vec<String>> {
+        self.hypothesis.symbols()
+    }
+
+    fn symbol_separator(&self) -> Option<String> {
+        self.hypothesis.symbol_separator()
+    }
+
+    fn initial_state(&self) -> Option<String> {
+        // reading the reversed word starts from every accepting state
+        let states = self.states.as_ref()?;
+        Some(self.subset(states, |state| self.hypothesis.is_accepting_state(state)))
+    }
+
+    fn transition(&self, state: &str, symbol: &String) -> Option<String> {
+        // the states whose transition on the symbol leads into the subset
+        let states = self.states.as_ref()?;
+        let members: BTreeSet<&String> = states.iter().zip(state.chars())
+            .filter(|(_, member)| *member == '1')
+            .map(|(state, _)| state)
+            .collect();
+        Some(self.subset(states, |source| {
+            self.hypothesis.transition(source, symbol).is_some_and(|target| members.contains(&target))
+        }))
+    }
+
+    fn is_accepting_state(&self, state: &str) -> bool {
+        let initial = self.hypothesis.initial_state();
+        self.states.iter().flatten().zip(state.chars())
+            .any(|(member, bit)| bit == '1' && Some(member) == initial.as_ref())
+    }
+}
+
+
+impl<O: OracleTrait<String>> OracleTrait<String> for ReverseOracle<O> {
+    fn membership_query(&self, input: &String) -> bool {
+        // a word not made of the symbols has no reverse over them, so it is not in the language
+        reverse(input, self.symbols.as_deref(), &self.separator)
+            .is_some_and(|reversed| self.inner.membership_query(&reversed))
+    }
+
+    fn equivalence_query<H: AutomatonTrait<String>>(&self, hypothesis: &H) -> Option<String> {
+        self.try_equivalence_query(hypothesis).ok().flatten()
+    }
+
+    fn try_equivalence_query<H: AutomatonTrait<String>>(&self, hypothesis: &H) -> Result<Option<String>, OracleFailure> {
+        // a word telling the inner language apart from the reversed hypothesis
+        // tells the reversed language apart from the hypothesis once reversed. the inner oracle
+        // spells it with the symbols and separator of the hypothesis, falling back to ours.
+        let symbols = hypothesis.symbols().or_else(|| self.symbols.clone());
+        let separator = hypothesis.symbol_separator().unwrap_or_else(|| self.separator.clone());
+        match self.inner.try_equivalence_query(&ReversedAutomaton::new(hypothesis))? {
+            None => Ok(None),
+            Some(counterexample) => reverse(&counterexample, symbols.as_deref(), &separator)
+                .map(Some)
+                .ok_or_else(|| format!("counterexample {counterexample} is not made of the symbols").into()),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+    use calf::learned_dfa::LearnedDfa;
+    use crate::dfa_oracle::DfaOracle;
+    use super::*;
+
+    fn symbols(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn words_are_reversed_symbol_by_symbol() {
+        let foo_bar = symbols(&["foo", "bar"]);
+        assert_eq!(reverse("foo bar bar", Some(&foo_bar), " "), Some("bar bar foo".to_string()));
+        assert_eq!(reverse("foobarbar", Some(&foo_bar), ""), Some("barbarfoo".to_string()));
+        assert_eq!(reverse("abc", None, ""), Some("cba".to_string()));
+        assert_eq!(reverse("a,bc", None, ","), Some("bc,a".to_string()));
+        assert_eq!(reverse("foo baz", Some(&foo_bar), " "), None);
+    }
+
+    #[test]
+    fn reversed_hypotheses_are_explored_as_dfas() {
+        // a b*, whose reversal is b* a
+        let name = |name: &str| name.to_string();
+        let transitions = [("s", "a", "a"), ("s", "b", "x"), ("a", "a", "x"), ("a", "b", "a"), ("x", "a", "x"), ("x", "b", "x")]
+            .iter()
+            .map(|(source, symbol, target)| ((name(source), name(symbol)), name(target)))
+            .collect();
+        let a_then_bs = LearnedDfa::new(
+            HashSet::from([name("s"), name("a"), name("x")]), symbols(&["a", "b"]), transitions, name("s"), HashSet::from([name("a")]));
+        let reversed = ReversedAutomaton::new(&a_then_bs);
+
+        let initial = reversed.initial_state().unwrap();
+        let after_a = reversed.transition(&initial, &name("a")).unwrap();
+        let after_b = reversed.transition(&initial, &name("b")).unwrap();
+        assert!(!reversed.is_accepting_state(&initial));
+        assert!(reversed.is_accepting_state(&after_a));
+        assert!(!reversed.is_accepting_state(&reversed.transition(&after_a, &name("b")).unwrap()));
+        assert!(reversed.is_accepting_state(&reversed.transition(&after_b, &name("a")).unwrap()));
+
+        // b* a as a DFA oracle, which searches the product with the reversal
+        let b_then_a = |accepting| ReverseOracle::new(DfaOracle::new(
+            HashSet::from([0, 1, 2]),
+            vec!['a', 'b'],
+            HashMap::from([((0, 'a'), 1), ((0, 'b'), 0), ((1, 'a'), 2), ((1, 'b'), 2), ((2, 'a'), 2), ((2, 'b'), 2)]),
+            0,
+            accepting,
+        ).unwrap());
+        assert_eq!(OracleTrait::equivalence_query(&b_then_a(HashSet::from([1])), &a_then_bs), None);
+        assert_eq!(OracleTrait::equivalence_query(&b_then_a(HashSet::from([0, 1])), &a_then_bs), Some(String::new()));
+    }
+}
@@ -0,0 +1,106 @@
+use calf::oracle_trait::{AutomatonTrait, OracleTrait};
+use crate::oracle_error::OracleError;
+
+/// Oracle for a strictly locally testable language: a word is accepted iff every
+/// window of `window` consecutive symbols satisfies the predicate.
+///
+/// Words shorter than the window are checked as a whole.
+pub struct WindowOracle {
+    alphabet: Vec<char>,
+    window: usize,
+    predicate: Box<dyn Fn(&str) -> bool + Send + Sync>,
+    max_counterexample_length: Option<usize>,
+}
+
+
+impl WindowOracle {
+    /// Creates a new `WindowOracle` over `alphabet` with the given window size and predicate.
+    pub fn new(
+        alphabet: Vec<char>,
+        window: usize,
+        predicate: impl Fn(&str) -> bool + Send + Sync + 'static,
+    ) -> Result<Self, OracleError> {
+        if window == 0 {
+            return Err(OracleError::InvalidWindowSize(window));
+        }
+        Ok(WindowOracle {
+            alphabet,
+            window,
+            predicate: Box::new(predicate),
+            max_counterexample_length: None,
+        })
+    }
+
+    /// Sets the length of the longest word tried by equivalence queries.
+    pub fn with_max_counterexample_length(mut self, max_counterexample_length: usize) -> Self {
+        self.max_counterexample_length = Some(max_counterexample_length);
+        self
+    }
+
+    /// Checks if every window of the input satisfies the predicate.
+    pub fn matches(&self, input: &str) -> bool {
+        let symbols: Vec<char> = input.chars().collect();
+        if symbols.len() < self.window {
+            return (self.predicate)(input);
+        }
+        symbols
+            .windows(self.window)
+            .all(|window| (self.predicate)(&window.iter().collect::<String>()))
+    }
+}
+
+
+impl OracleTrait<String> for WindowOracle {
+    fn membership_query(&self, input: &String) -> bool {
+        self.matches(input)
+    }
+
+    fn equivalence_query<H: AutomatonTrait<String>>(&self, hypothesis: &H) -> Option<String> {
+        self.find_counterexample_by_enumeration(hypothesis, &self.alphabet)
+    }
+
+    fn search_bound(&self) -> Option<usize> {
+        self.max_counterexample_length
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use super::*;
+
+    // no two consecutive a's
+    fn no_aa() -> WindowOracle {
+        WindowOracle::new(vec!['a', 'b'], 2, |window| window != "aa").unwrap()
+    }
+
+    #[test]
+    fn every_window_is_checked() {
+        let oracle = no_aa();
+        assert!(oracle.matches("abab"));
+        assert!(oracle.matches("ba"));
+        assert!(!oracle.matches("abaab"));
+        assert!(!oracle.matches("baa"));
+    }
+
+    #[test]
+    fn words_shorter_than_the_window_are_checked_whole() {
+        let asked = Arc::new(Mutex::new(vec![]));
+        let recorded = asked.clone();
+        let oracle = WindowOracle::new(vec!['a', 'b'], 3, move |window| {
+            recorded.lock().unwrap().push(window.to_string());
+            !window.starts_with('b')
+        }).unwrap();
+
+        assert!(oracle.matches(""));
+        assert!(oracle.matches("ab"));
+        assert!(!oracle.matches("ba"));
+        assert_eq!(*asked.lock().unwrap(), vec!["", "ab", "ba"]);
+    }
+
+    #[test]
+    fn empty_windows_are_rejected() {
+        assert!(matches!(WindowOracle::new(vec!['a'], 0, |_| true), Err(OracleError::InvalidWindowSize(0))));
+    }
+}